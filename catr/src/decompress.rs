@@ -0,0 +1,42 @@
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use xz2::read::XzDecoder;
+use crate::BUF_SIZE;
+
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const BZIP2_MAGIC: &[u8] = b"BZh";
+/// The block size that follows `BZIP2_MAGIC`, so text that happens to
+/// start with "BZh" isn't taken for bzip2.
+const BZIP2_BLOCK_SIZES: std::ops::RangeInclusive<u8> = b'1'..=b'9';
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+/// Long enough for the longest magic number above.
+const MAGIC_LEN: u64 = 6;
+
+
+/// Wraps `input` in a decoder if it starts with a gzip, bzip2, xz or zstd
+/// magic number, and otherwise passes it through untouched. Concatenated
+/// streams, as `cat a.gz b.gz` makes, are read to the end.
+pub fn decompress(mut input: Box<dyn BufRead>) -> io::Result<Box<dyn BufRead>> {
+    // Reading the magic number out rather than peeking at the buffer, since
+    // a pipe may hand over fewer bytes than that at first.
+    let mut magic = vec![];
+    input.by_ref().take(MAGIC_LEN).read_to_end(&mut magic)?;
+    let input = Cursor::new(magic.clone()).chain(input);
+    let decoder: Box<dyn Read> = if magic.starts_with(GZIP_MAGIC) {
+        Box::new(MultiGzDecoder::new(input))
+    } else if magic.starts_with(BZIP2_MAGIC)
+        && magic.get(3).is_some_and(|size| BZIP2_BLOCK_SIZES.contains(size))
+    {
+        Box::new(MultiBzDecoder::new(input))
+    } else if magic.starts_with(XZ_MAGIC) {
+        Box::new(XzDecoder::new_multi_decoder(input))
+    } else if magic.starts_with(ZSTD_MAGIC) {
+        Box::new(zstd::Decoder::with_buffer(input)?)
+    } else {
+        return Ok(Box::new(input));
+    };
+    Ok(Box::new(BufReader::with_capacity(BUF_SIZE, decoder)))
+}
@@ -0,0 +1,76 @@
+use std::fs::{self, File, Metadata};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::thread;
+use std::time::Duration;
+use crate::{cat_lines, copy, Config, Failure, LineState, BUF_SIZE};
+
+
+/// How long to wait at the end of the file before looking for more.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+
+/// Prints `filename` and then whatever is appended to it, until killed.
+/// When the file is truncated it's read again from the start, and when
+/// it's replaced, as log rotation does, the rest of the old file is
+/// printed before switching to the new one.
+pub fn follow(
+    config: &Config,
+    state: &mut LineState,
+    filename: &str,
+    out: &mut impl Write,
+) -> Result<(), Failure> {
+    let file = File::open(filename).map_err(Failure::Input)?;
+    let mut id = file_id(&file.metadata().map_err(Failure::Input)?);
+    let mut reader = BufReader::with_capacity(BUF_SIZE, file);
+    loop {
+        print_available(config, state, &mut reader, out)?;
+        thread::sleep(POLL_INTERVAL);
+
+        // The file may be missing for a moment while it's being rotated.
+        let Ok(metadata) = fs::metadata(filename) else {
+            continue;
+        };
+        if file_id(&metadata) != id {
+            print_available(config, state, &mut reader, out)?;
+            eprintln!("{}: file replaced, following the new file", filename);
+            let file = File::open(filename).map_err(Failure::Input)?;
+            id = file_id(&file.metadata().map_err(Failure::Input)?);
+            reader = BufReader::with_capacity(BUF_SIZE, file);
+        } else if metadata.len() < reader.stream_position().map_err(Failure::Input)? {
+            eprintln!("{}: file truncated", filename);
+            reader.seek(SeekFrom::Start(0)).map_err(Failure::Input)?;
+        }
+    }
+}
+
+
+/// Prints the file up to its current end, leaving `reader` there.
+fn print_available(
+    config: &Config,
+    state: &mut LineState,
+    reader: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<(), Failure> {
+    if config.formats() {
+        cat_lines(config, state, reader, out)?;
+    } else {
+        copy(reader, out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+
+/// Identifies the file behind a path, so a new file put in its place can
+/// be told apart.
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
@@ -1,7 +1,11 @@
 use std::error::Error;
 use clap::{Arg, ArgAction, Command, value_parser};
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+
+mod decompress;
+mod follow;
+mod pretty;
 
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
@@ -12,43 +16,200 @@ pub struct Config {
     files: Vec<String>,
     number_lines: bool,
     number_nonblank_lines: bool,
+    number_per_file: bool,
+    show_ends: bool,
+    show_tabs: bool,
+    show_nonprinting: bool,
+    squeeze_blank: bool,
+    pretty: bool,
+    decompress: bool,
+    follow: bool,
+}
+
+
+/// How much is read or written at a time.
+const BUF_SIZE: usize = 128 * 1024;
+
+
+impl Config {
+    /// Whether any option changes the output, so the input has to be
+    /// processed line by line instead of copied as is.
+    fn formats(&self) -> bool {
+        self.number_lines
+            || self.number_nonblank_lines
+            || self.show_ends
+            || self.show_tabs
+            || self.show_nonprinting
+            || self.squeeze_blank
+    }
+}
+
+
+/// Where the output stands, carried from one file to the next so numbering
+/// and squeezing continue across them like one stream.
+#[derive(Debug)]
+struct LineState {
+    line_num: usize,
+    prev_blank: bool,
+    /// Whether the last file ended in a newline, so the next line written
+    /// starts a new output line rather than continuing one.
+    line_start: bool,
+}
+
+
+/// Why a file wasn't printed in full. A file that can't be read is
+/// reported and skipped, but output that can't be written stops catr.
+#[derive(Debug)]
+enum Failure {
+    Input(io::Error),
+    Output(io::Error),
 }
 
 
-pub fn run(config: Config) -> MyResult<()> {
-    for filename in config.files {
-        match open(&filename) {
-            Err(err) => {
-                eprintln!("Failed to open {}: {}", filename, err);
+impl From<io::Error> for Failure {
+    fn from(err: io::Error) -> Self {
+        Failure::Output(err)
+    }
+}
+
+
+/// Prints every file, reporting the ones that can't be read on stderr.
+/// Returns whether all of them were read.
+pub fn run(config: Config) -> MyResult<bool> {
+    if config.pretty && io::stdout().is_terminal() {
+        return pretty::run(&config);
+    }
+    let mut out = io::BufWriter::with_capacity(BUF_SIZE, io::stdout().lock());
+    let mut state = LineState { line_num: 0, prev_blank: false, line_start: true };
+    let mut all_read = true;
+    for filename in &config.files {
+        // A file that ends mid-line is continued by the next one, whose
+        // numbering starts with the first line that begins in it.
+        if config.number_per_file {
+            state.line_num = 0;
+        }
+        let result = if config.follow {
+            follow::follow(&config, &mut state, filename, &mut out)
+        } else {
+            match open(filename, config.decompress) {
+                Err(err) => Err(Failure::Input(err)),
+                Ok(file) if !config.formats() => copy(file, &mut out),
+                Ok(file) => cat_lines(&config, &mut state, file, &mut out),
+            }
+        };
+        match result {
+            Ok(()) => {},
+            Err(Failure::Input(err)) => {
+                // Keep the message next to the output it interrupted.
+                out.flush()?;
+                eprintln!("{}: {}", filename, err);
+                all_read = false;
             },
-            Ok(file) => {
-                let mut skipblank = 0;
-                for (idx, line) in file.lines().enumerate() {
-                    let line = line?;
-                    if config.number_lines {
-                        println!("{:>6}\t{}", (idx + 1), line);
-                    } else if config.number_nonblank_lines {
-                        if line.is_empty() {
-                            println!();
-                            skipblank = skipblank + 1;
-                        } else {
-                            println!("{:>6}\t{}", (idx + 1 - skipblank), line);
-                        }
-                    } else {
-                        println!("{}", line);
+            Err(Failure::Output(err)) => return Err(From::from(err)),
+        }
+    }
+    out.flush()?;
+    Ok(all_read)
+}
+
+
+/// Copies `file` to `out` unchanged.
+fn copy(mut file: impl BufRead, out: &mut impl Write) -> Result<(), Failure> {
+    loop {
+        let buf = match file.fill_buf() {
+            Ok([]) => return Ok(()),
+            Ok(buf) => buf,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Failure::Input(err)),
+        };
+        out.write_all(buf)?;
+        let len = buf.len();
+        file.consume(len);
+    }
+}
+
+
+/// Writes `file` to `out` a line at a time, as bytes so nothing but the
+/// requested formatting changes. A last line without a newline is
+/// written without one.
+fn cat_lines(
+    config: &Config,
+    state: &mut LineState,
+    mut file: impl BufRead,
+    out: &mut impl Write,
+) -> Result<(), Failure> {
+    let mut line = vec![];
+    while file.read_until(b'\n', &mut line).map_err(Failure::Input)? > 0 {
+        let (content, newline) = match line.strip_suffix(b"\n") {
+            Some(content) => (content, true),
+            None => (&line[..], false),
+        };
+        let blank = content.is_empty();
+        if !(config.squeeze_blank && blank && state.prev_blank) {
+            if state.line_start && (config.number_lines || (config.number_nonblank_lines && !blank)) {
+                state.line_num += 1;
+                write!(out, "{:>6}\t", state.line_num)?;
+            }
+            // Like GNU cat, `-E` shows the `\r` of a CRLF ending as `^M`.
+            match content.strip_suffix(b"\r") {
+                Some(content) if config.show_ends && newline => {
+                    out.write_all(&display(content, config))?;
+                    out.write_all(b"^M$")?;
+                }
+                _ => {
+                    out.write_all(&display(content, config))?;
+                    if config.show_ends && newline {
+                        out.write_all(b"$")?;
                     }
                 }
             }
+            if newline {
+                out.write_all(b"\n")?;
+            }
         }
+        state.prev_blank = blank;
+        state.line_start = newline;
+        line.clear();
     }
     Ok(())
 }
 
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+/// Shows tabs as `^I` with `-T`, and with `-v` other control characters as
+/// `^X`, DEL as `^?` and bytes above 127 as `M-` plus the same notation
+/// for their low seven bits.
+fn display(line: &[u8], config: &Config) -> Vec<u8> {
+    let mut shown = Vec::with_capacity(line.len());
+    for &byte in line {
+        match byte {
+            b'\t' if config.show_tabs => shown.extend_from_slice(b"^I"),
+            b'\t' => shown.push(byte),
+            _ if !config.show_nonprinting => shown.push(byte),
+            _ => {
+                if byte >= 128 {
+                    shown.extend_from_slice(b"M-");
+                }
+                match byte & 0x7f {
+                    low @ 0..=31 => shown.extend_from_slice(&[b'^', low + 64]),
+                    127 => shown.extend_from_slice(b"^?"),
+                    low => shown.push(low),
+                }
+            }
+        }
+    }
+    shown
+}
+
+
+fn open(filename: &str, decompress: bool) -> io::Result<Box<dyn BufRead>> {
+    let file: Box<dyn BufRead> = match filename {
+        "-" => Box::new(BufReader::with_capacity(BUF_SIZE, io::stdin())),
+        _ => Box::new(BufReader::with_capacity(BUF_SIZE, File::open(filename)?)),
+    };
+    if decompress {
+        decompress::decompress(file)
+    } else {
+        Ok(file)
     }
 }
 
@@ -69,6 +230,60 @@ pub fn get_args() -> MyResult<Config> {
              .value_parser(value_parser!(bool))
              .conflicts_with("number")
              .help("Number nonblank lines"))
+        .arg(Arg::new("number_per_file")
+             .long("number-per-file")
+             .action(ArgAction::SetTrue)
+             .value_parser(value_parser!(bool))
+             .help("Restart line numbers at 1 for each file"))
+        .arg(Arg::new("show_all")
+             .long("show-all")
+             .short('A')
+             .action(ArgAction::SetTrue)
+             .value_parser(value_parser!(bool))
+             .help("Same as -vET"))
+        .arg(Arg::new("show_ends")
+             .long("show-ends")
+             .short('E')
+             .action(ArgAction::SetTrue)
+             .value_parser(value_parser!(bool))
+             .help("Show $ at the end of each line"))
+        .arg(Arg::new("show_tabs")
+             .long("show-tabs")
+             .short('T')
+             .action(ArgAction::SetTrue)
+             .value_parser(value_parser!(bool))
+             .help("Show tabs as ^I"))
+        .arg(Arg::new("show_nonprinting")
+             .long("show-nonprinting")
+             .short('v')
+             .action(ArgAction::SetTrue)
+             .value_parser(value_parser!(bool))
+             .help("Use ^ and M- notation, except for line feeds and tabs"))
+        .arg(Arg::new("squeeze_blank")
+             .long("squeeze-blank")
+             .short('s')
+             .action(ArgAction::SetTrue)
+             .value_parser(value_parser!(bool))
+             .help("Suppress repeated empty output lines"))
+        .arg(Arg::new("pretty")
+             .long("pretty")
+             .action(ArgAction::SetTrue)
+             .value_parser(value_parser!(bool))
+             .conflicts_with_all(["number_nonblank", "show_all", "show_ends", "show_tabs", "show_nonprinting", "squeeze_blank"])
+             .help("Highlight syntax, show git changes and page the output on a terminal"))
+        .arg(Arg::new("decompress")
+             .long("decompress")
+             .short('z')
+             .action(ArgAction::SetTrue)
+             .value_parser(value_parser!(bool))
+             .help("Decompress gzip, bzip2, xz and zstd input, detected by content"))
+        .arg(Arg::new("follow")
+             .long("follow")
+             .short('f')
+             .action(ArgAction::SetTrue)
+             .value_parser(value_parser!(bool))
+             .conflicts_with_all(["decompress", "pretty"])
+             .help("Keep printing what is appended to FILE, following truncation and rotation"))
         .arg(Arg::new("file")
             .value_name("FILE")
             .value_parser(value_parser!(String))
@@ -78,9 +293,22 @@ pub fn get_args() -> MyResult<Config> {
     ).get_matches();
 
     let files: Vec<_> = matches.get_many::<String>("file").unwrap_or_default().map(|x| x.to_owned()).collect();
+    let follow = matches.get_flag("follow");
+    if follow && (files.len() != 1 || files[0] == "-") {
+        return Err(From::from("--follow needs a single file other than stdin"));
+    }
+    let show_all = matches.get_flag("show_all");
     Ok(Config {
         files,
         number_lines: matches.get_flag("number"),
         number_nonblank_lines: matches.get_flag("number_nonblank"),
+        number_per_file: matches.get_flag("number_per_file"),
+        show_ends: show_all || matches.get_flag("show_ends"),
+        show_tabs: show_all || matches.get_flag("show_tabs"),
+        show_nonprinting: show_all || matches.get_flag("show_nonprinting"),
+        squeeze_blank: matches.get_flag("squeeze_blank"),
+        pretty: matches.get_flag("pretty"),
+        decompress: matches.get_flag("decompress"),
+        follow,
     })
 }
@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::as_24_bit_terminal_escaped;
+use crate::{Config, Failure, MyResult};
+
+
+const THEME: &str = "base16-ocean.dark";
+/// Line number, space, change marker, space.
+const GUTTER_WIDTH: usize = 7;
+
+
+/// How a line differs from the version of the file git has staged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineChange {
+    Added,
+    Modified,
+    /// Lines were deleted just above this one.
+    RemovedAbove,
+}
+
+
+/// Prints the files with a header, a gutter of line numbers and git changes,
+/// and syntax highlighting, through `$PAGER` or `less` if there is one.
+/// Returns whether all of them were read, like `crate::run`.
+pub fn run(config: &Config) -> MyResult<bool> {
+    let syntaxes = SyntaxSet::load_defaults_newlines();
+    let themes = ThemeSet::load_defaults();
+    let theme = &themes.themes[THEME];
+    let width = terminal_size::terminal_size().map_or(80, |(width, _)| width.0 as usize);
+    // Rendered up front, so it can still go to the terminal if the pager
+    // turns out not to exist, without reading the files twice.
+    let mut shown = vec![];
+    let all_read = print_files(config, &mut shown, &syntaxes, theme, width)?;
+
+    let failure = match spawn_pager() {
+        Ok(None) => {
+            io::stdout().lock().write_all(&shown)?;
+            return Ok(all_read);
+        }
+        Ok(Some(mut pager)) => {
+            let mut stdin = pager.stdin.take().expect("the pager's stdin is piped");
+            let written = stdin.write_all(&shown);
+            drop(stdin);
+            let status = pager.wait()?;
+            // The shell's code for a command it couldn't find or run.
+            if status.code() != Some(127) {
+                return match written {
+                    // The pager was quit before reading everything.
+                    Err(err) if err.kind() != io::ErrorKind::BrokenPipe => Err(From::from(err)),
+                    _ => Ok(all_read),
+                };
+            }
+            status.to_string()
+        }
+        Err(err) => err.to_string(),
+    };
+    io::stdout().lock().write_all(&shown)?;
+    Err(From::from(format!("pager failed ({}), printed without it", failure)))
+}
+
+
+/// Renders each file in turn into `out`, reporting the ones that can't be
+/// read on stderr, and returns whether all of them were.
+fn print_files(
+    config: &Config,
+    out: &mut Vec<u8>,
+    syntaxes: &SyntaxSet,
+    theme: &Theme,
+    width: usize,
+) -> io::Result<bool> {
+    let mut all_read = true;
+    for filename in &config.files {
+        let printed = crate::open(filename, config.decompress)
+            .map_err(Failure::Input)
+            .and_then(|file| print_file(out, filename, file, syntaxes, theme, width));
+        match printed {
+            Ok(()) => {}
+            Err(Failure::Input(err)) => {
+                eprintln!("{}: {}", filename, err);
+                all_read = false;
+            }
+            Err(Failure::Output(err)) => return Err(err),
+        }
+    }
+    Ok(all_read)
+}
+
+
+fn print_file(
+    out: &mut impl Write,
+    filename: &str,
+    mut file: Box<dyn BufRead>,
+    syntaxes: &SyntaxSet,
+    theme: &Theme,
+    width: usize,
+) -> Result<(), Failure> {
+    let mut contents = vec![];
+    file.read_to_end(&mut contents).map_err(Failure::Input)?;
+    let text = String::from_utf8_lossy(&contents);
+    let (name, changes) = match filename {
+        "-" => ("STDIN", HashMap::new()),
+        _ => (filename, git_changes(Path::new(filename))),
+    };
+
+    rule(out, '┬', width)?;
+    writeln!(out, "{:w$}│ File: \x1b[1m{}\x1b[0m", "", name, w = GUTTER_WIDTH)?;
+    rule(out, '┼', width)?;
+    let syntax = syntax_for(syntaxes, Path::new(filename), text.lines().next().unwrap_or_default());
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    for (idx, line) in text.split_inclusive('\n').enumerate() {
+        let marker = match changes.get(&(idx + 1)) {
+            Some(LineChange::Added) => "\x1b[32m+\x1b[0m",
+            Some(LineChange::Modified) => "\x1b[33m~\x1b[0m",
+            Some(LineChange::RemovedAbove) => "\x1b[31m_\x1b[0m",
+            None => " ",
+        };
+        let ranges: Vec<_> = highlighter
+            .highlight_line(line, syntaxes)
+            .map_err(|err| Failure::Input(io::Error::new(io::ErrorKind::InvalidData, err)))?
+            .into_iter()
+            .map(|(style, text)| (style, text.trim_end_matches(['\r', '\n'])))
+            .collect();
+        writeln!(
+            out,
+            "\x1b[2m{:>4}\x1b[0m {} │ {}\x1b[0m",
+            idx + 1,
+            marker,
+            as_24_bit_terminal_escaped(&ranges, false)
+        )?;
+    }
+    rule(out, '┴', width)?;
+    Ok(())
+}
+
+
+/// Draws a horizontal line across the terminal, crossing the gutter's
+/// edge with `joint`.
+fn rule(out: &mut impl Write, joint: char, width: usize) -> io::Result<()> {
+    writeln!(
+        out,
+        "{}{}{}",
+        "─".repeat(GUTTER_WIDTH),
+        joint,
+        "─".repeat(width.saturating_sub(GUTTER_WIDTH + 1))
+    )
+}
+
+
+/// Picks a syntax by extension, then by file name (`Makefile`), then by
+/// a shebang or similar on the first line.
+fn syntax_for<'a>(syntaxes: &'a SyntaxSet, path: &Path, first_line: &str) -> &'a SyntaxReference {
+    let by_name = |name: Option<&std::ffi::OsStr>| {
+        name.and_then(|name| name.to_str()).and_then(|name| syntaxes.find_syntax_by_extension(name))
+    };
+    by_name(path.extension())
+        .or_else(|| by_name(path.file_name()))
+        .or_else(|| syntaxes.find_syntax_by_first_line(first_line))
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text())
+}
+
+
+/// Finds the lines of `path` that differ from what git has staged, by
+/// line number. Files outside a repository, or without git, have none.
+fn git_changes(path: &Path) -> HashMap<usize, LineChange> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return HashMap::new();
+    };
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let output = Command::new("git")
+        .args(["diff", "--no-ext-diff", "--no-color", "--unified=0", "--"])
+        .arg(name)
+        .current_dir(dir)
+        .stderr(Stdio::null())
+        .output();
+    match output {
+        Ok(output) => parse_diff(&String::from_utf8_lossy(&output.stdout)),
+        Err(_) => HashMap::new(),
+    }
+}
+
+
+/// Reads the hunk headers of a `--unified=0` diff into changes by line
+/// number in the new file.
+fn parse_diff(diff: &str) -> HashMap<usize, LineChange> {
+    let mut changes = HashMap::new();
+    for line in diff.lines() {
+        // A hunk header looks like `@@ -old,len +new,len @@`, where a
+        // missing length means 1.
+        let Some(hunk) = line.strip_prefix("@@ -") else {
+            continue;
+        };
+        let mut ranges = hunk.split(' ');
+        let old = ranges.next().and_then(parse_hunk_range);
+        let new = ranges.next().and_then(|range| range.strip_prefix('+')).and_then(parse_hunk_range);
+        let (Some((_, old_len)), Some((start, len))) = (old, new) else {
+            continue;
+        };
+        if len == 0 {
+            // Deleted lines are placed after `start`, which is 0 at the top.
+            changes.insert(start + 1, LineChange::RemovedAbove);
+        } else {
+            let change = if old_len == 0 { LineChange::Added } else { LineChange::Modified };
+            changes.extend((start..start + len).map(|n| (n, change)));
+        }
+    }
+    changes
+}
+
+
+fn parse_hunk_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+
+/// Starts `$PAGER`, or `less` when that's unset, reading from a pipe.
+/// Without either, there's nothing to start.
+fn spawn_pager() -> io::Result<Option<Child>> {
+    let mut cmd = match env::var("PAGER") {
+        Ok(pager) if !pager.trim().is_empty() => {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c").arg(pager);
+            cmd
+        }
+        _ => match find_in_path("less") {
+            Some(less) => Command::new(less),
+            None => return Ok(None),
+        },
+    };
+    cmd.stdin(Stdio::piped());
+    if env::var_os("LESS").is_none() {
+        // Keep colors, and exit straight away when it all fits on screen.
+        cmd.env("LESS", "FRX");
+    }
+    cmd.spawn().map(Some)
+}
+
+
+/// Looks for the program `name` in the directories of `$PATH`.
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_diff, LineChange};
+    use std::collections::HashMap;
+
+    const HEADER: &str = "diff --git a/f.rs b/f.rs\n--- a/f.rs\n+++ b/f.rs\n";
+
+    fn changes(hunks: &str) -> Vec<(usize, LineChange)> {
+        let mut changes: Vec<_> = parse_diff(&format!("{}{}", HEADER, hunks)).into_iter().collect();
+        changes.sort_by_key(|(line, _)| *line);
+        changes
+    }
+
+    #[test]
+    fn added_lines() {
+        assert_eq!(
+            changes("@@ -3,0 +4,2 @@ fn main() {\n+    one();\n+    two();\n"),
+            [(4, LineChange::Added), (5, LineChange::Added)]
+        );
+    }
+
+    #[test]
+    fn modified_lines() {
+        assert_eq!(
+            changes("@@ -2,2 +2,3 @@\n-a\n-b\n+a\n+b\n+c\n"),
+            [(2, LineChange::Modified), (3, LineChange::Modified), (4, LineChange::Modified)]
+        );
+    }
+
+    #[test]
+    fn removed_lines() {
+        assert_eq!(changes("@@ -5,2 +4,0 @@\n-a\n-b\n"), [(5, LineChange::RemovedAbove)]);
+        // Removed from the top of the file.
+        assert_eq!(changes("@@ -1 +0,0 @@\n-a\n"), [(1, LineChange::RemovedAbove)]);
+    }
+
+    #[test]
+    fn hunks_without_length() {
+        assert_eq!(
+            changes("@@ -7 +7 @@\n-a\n+b\n@@ -9,0 +10 @@\n+c\n"),
+            [(7, LineChange::Modified), (10, LineChange::Added)]
+        );
+    }
+
+    #[test]
+    fn no_changes() {
+        assert_eq!(parse_diff(""), HashMap::new());
+        assert_eq!(parse_diff("@@ -x +1 @@\n"), HashMap::new());
+    }
+}
@@ -47,7 +47,7 @@ fn skips_bad_file() -> TestResult {
     Command::cargo_bin(PRG)?
         .arg(&bad)
         .assert()
-        .success()
+        .failure()
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
 }
@@ -198,3 +198,316 @@ fn all_n() -> TestResult {
 fn all_b() -> TestResult {
     run(&[FOX, SPIDERS, BUSTLE, "-b"], "tests/expected/all.b.out")
 }
+
+// --------------------------------------------------
+fn run_input(args: &[&str], input: &str, expected: &str) -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected.to_string());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn show_all() -> TestResult {
+    run_input(&["-A"], "a\tb\x01\x7f\u{e9}\n", "a^Ib^A^?M-CM-)$\n")?;
+    run_input(&["-E", "-T"], "a\tb\u{e9}\n", "a^Ib\u{e9}$\n")?;
+    run_input(&["--show-nonprinting"], "a\tb\x1b\n", "a\tb^[\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn squeeze_blank() -> TestResult {
+    run_input(&["-s"], "a\n\n\n\nb\n\n", "a\n\nb\n\n")?;
+    run_input(&["-s", "-n"], "a\n\n\nb\n", "     1\ta\n     2\t\n     3\tb\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn crlf_lines() -> TestResult {
+    // As GNU cat 9.1 prints them: a `\r` alone isn't a blank line.
+    let input = "a\r\n\r\n\r\nb\r\r\n";
+    run_input(&["-E"], input, "a^M$\n^M$\n^M$\nb\r^M$\n")?;
+    run_input(&["-A"], input, "a^M$\n^M$\n^M$\nb^M^M$\n")?;
+    run_input(
+        &["-b"],
+        input,
+        "     1\ta\r\n     2\t\r\n     3\t\r\n     4\tb\r\r\n",
+    )?;
+    run_input(&["-s"], input, input)?;
+    run_input(&["-E"], "a\r", "a\r")
+}
+
+// --------------------------------------------------
+fn run_bytes(args: &[&str], input: &[u8], expected: &[u8]) -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected.to_vec());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_passthrough() -> TestResult {
+    let input: Vec<u8> = (0..=255).chain([b'\r', b'\n', 0xff, b'x']).collect();
+    run_bytes(&[], &input, &input)?;
+    run_bytes(&["-", "-"], b"a\r\nb", b"a\r\nb")
+}
+
+// --------------------------------------------------
+#[test]
+fn numbered_bytes() -> TestResult {
+    run_bytes(&["-n"], b"\xff\r\nlast", b"     1\t\xff\r\n     2\tlast")?;
+    run_bytes(&["-E"], b"a\nb", b"a$\nb")
+}
+
+// --------------------------------------------------
+#[test]
+fn all_n_per_file() -> TestResult {
+    run(
+        &[FOX, SPIDERS, BUSTLE, "-n", "--number-per-file"],
+        "tests/expected/all.n.per-file.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn per_file_without_newline() -> TestResult {
+    let dir = std::env::temp_dir().join(format!("catr-{}", gen_bad_file()));
+    fs::create_dir(&dir)?;
+    fs::write(dir.join("a"), "x")?;
+    fs::write(dir.join("b"), "y\nz\n")?;
+    let files = [dir.join("a"), dir.join("b")];
+    let result = Command::cargo_bin(PRG)?
+        .args(["-n", "--number-per-file"])
+        .args(&files)
+        .assert()
+        .try_success();
+    fs::remove_dir_all(&dir)?;
+    result?.stdout("     1\txy\n     1\tz\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn all_b_per_file() -> TestResult {
+    run(
+        &[FOX, SPIDERS, BUSTLE, "-b", "--number-per-file"],
+        "tests/expected/all.b.per-file.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn pretty_when_piped() -> TestResult {
+    run(&["--pretty", FOX, SPIDERS, BUSTLE], "tests/expected/all.out")?;
+    run(&["--pretty", "-n", BUSTLE], "tests/expected/the-bustle.txt.n.out")
+}
+
+// --------------------------------------------------
+/// Runs catr with `args` on a pseudo-terminal through `script`, returning
+/// whether it succeeded and what the terminal showed. Returns `None` when
+/// `script` isn't installed.
+#[cfg(target_os = "linux")]
+fn run_on_tty(
+    args: &[&str],
+    pager: &str,
+) -> Result<Option<(bool, String)>, Box<dyn Error>> {
+    let command = format!(
+        "stty rows 40 cols 100; {} {}",
+        assert_cmd::cargo::cargo_bin(PRG).display(),
+        args.join(" ")
+    );
+    let output = match std::process::Command::new("script")
+        .args(["-qec", &command, "/dev/null"])
+        .env("PAGER", pager)
+        .output()
+    {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        output => output?,
+    };
+    let shown = String::from_utf8_lossy(&output.stdout).into_owned();
+    Ok(Some((output.status.success(), shown)))
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn pretty_on_tty() -> TestResult {
+    let Some((ok, shown)) = run_on_tty(&["--pretty", FOX], "cat")? else {
+        return Ok(());
+    };
+    assert!(ok);
+    assert!(shown.contains("File: \x1b[1mtests/inputs/fox.txt"));
+    assert!(shown.contains("The quick brown fox"));
+
+    // A pager that ran is trusted to have shown everything, whatever it
+    // exits with.
+    let Some((ok, shown)) = run_on_tty(&["--pretty", FOX], "cat; exit 3")? else {
+        return Ok(());
+    };
+    assert!(ok);
+    assert_eq!(shown.matches("The quick brown fox").count(), 1);
+
+    // Without a pager the files are still printed, once, but it's an error.
+    let Some((ok, shown)) = run_on_tty(&["--pretty", FOX], "does-not-exist")? else {
+        return Ok(());
+    };
+    assert!(!ok);
+    assert_eq!(shown.matches("The quick brown fox").count(), 1);
+    assert!(shown.contains("pager failed"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_pretty_with_formatting() -> TestResult {
+    for flag in ["-b", "-A", "-E", "-T", "-v", "-s"] {
+        Command::cargo_bin(PRG)?
+            .args(["--pretty", flag, FOX])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn decompress() -> TestResult {
+    for ext in ["gz", "bz2", "xz", "zst"] {
+        let compressed = format!("{}.{}", FOX, ext);
+        run(&["-z", &compressed], "tests/expected/fox.txt.out")?;
+        run(&["--decompress", "-n", &compressed], "tests/expected/fox.txt.n.out")?;
+    }
+    run(&["-z", FOX], "tests/expected/fox.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn decompress_stdin() -> TestResult {
+    let input = fs::read("tests/inputs/fox.txt.gz")?;
+    let expected = fs::read_to_string("tests/expected/fox.txt.b.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-b", "-"])
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout(expected);
+    run_bytes(&[], &input, &input)
+}
+
+// --------------------------------------------------
+#[test]
+fn decompress_plain_text() -> TestResult {
+    run_input(&["-z"], "BZh is my note\n", "BZh is my note\n")?;
+    run_input(&["-z"], "BZh", "BZh")
+}
+
+// --------------------------------------------------
+#[test]
+fn continues_after_bad_files() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = fs::read_to_string("tests/expected/fox.txt.out")?;
+    Command::cargo_bin(PRG)?
+        .args([&bad, "tests/inputs", FOX])
+        .assert()
+        .failure()
+        .stdout(expected)
+        .stderr(format!(
+            "{}: No such file or directory (os error 2)\ntests/inputs: Is a directory (os error 21)\n",
+            bad
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn reports_corrupt_input() -> TestResult {
+    let input = fs::read("tests/inputs/fox.txt.gz")?;
+    let fox = fs::read_to_string("tests/expected/fox.txt.out")?;
+    // What was decoded before the stream broke off is still printed.
+    let expected = format!("The quick brown fox{}", fox);
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-", FOX])
+        .write_stdin(&input[..30])
+        .assert()
+        .failure()
+        .stdout(expected)
+        .stderr("-: incomplete deflate stream\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn follow() -> TestResult {
+    use std::io::{BufRead, BufReader, Write};
+    use std::process::Stdio;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let dir = std::env::temp_dir().join(format!("catr-{}", gen_bad_file()));
+    fs::create_dir(&dir)?;
+    let log = dir.join("app.log");
+    fs::write(&log, "one\n")?;
+
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+        .args(["-f", "-n", log.to_str().unwrap()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let (tx, rx) = mpsc::channel();
+    let stdout = child.stdout.take().unwrap();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            if tx.send(line.unwrap()).is_err() {
+                break;
+            }
+        }
+    });
+    let next_line = || rx.recv_timeout(Duration::from_secs(5));
+    let append = |text: &str| -> TestResult {
+        fs::OpenOptions::new().append(true).open(&log)?.write_all(text.as_bytes())?;
+        Ok(())
+    };
+
+    let result = (|| -> TestResult {
+        assert_eq!(next_line()?, "     1\tone");
+        append("two\n")?;
+        assert_eq!(next_line()?, "     2\ttwo");
+        fs::write(&log, "")?;
+        std::thread::sleep(Duration::from_millis(500));
+        append("truncated\n")?;
+        assert_eq!(next_line()?, "     3\ttruncated");
+        fs::rename(&log, dir.join("app.log.1"))?;
+        fs::write(&log, "rotated\n")?;
+        assert_eq!(next_line()?, "     4\trotated");
+        Ok(())
+    })();
+    child.kill()?;
+    child.wait()?;
+    fs::remove_dir_all(&dir)?;
+    result
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_follow_stdin() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "-"])
+        .assert()
+        .failure()
+        .stderr("--follow needs a single file other than stdin\n");
+    Command::cargo_bin(PRG)?
+        .args(["-f", FOX, SPIDERS])
+        .assert()
+        .failure();
+    Ok(())
+}
@@ -1,28 +1,262 @@
-use clap::{Command, Arg, ArgAction};
+use clap::{builder, Command, Arg, ArgAction, ValueEnum, value_parser};
 use std::error::Error;
+use std::collections::HashSet;
+use std::cmp::Ordering;
 use std::ops::Range;
 use std::str::FromStr;
-use regex::Regex;
-use std::io::{self, BufReader, BufRead};
+use std::io::{self, BufReader, BufRead, Write};
 use std::fs::File;
-use csv::{ReaderBuilder,StringRecord, WriterBuilder};
+use csv::{ReaderBuilder, StringRecord, Terminator, WriterBuilder};
+use regex::Regex;
+use serde::Deserialize;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
+mod selector;
+
+pub use selector::{Selector, SelectorError};
+use selector::{clamp, extract_bytes, extract_chars, extract_fields, normalize, ListOrder, PositionList};
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
-type PositionList = Vec<Range<usize>>;
 
+/// What gets printed. By default lists are sorted and merged like GNU cut
+/// does, so `3,1` prints fields 1 and 3 in input order and `1,1` prints
+/// field 1 once. With `--reorder` they're printed as listed instead.
 #[derive(Debug)]
 pub enum Extract {
     Fields(PositionList),
+    /// Column names or `name-name` ranges, resolved against each file's
+    /// header row.
+    Names(Vec<String>),
     Bytes(PositionList),
     Chars(PositionList),
+    /// Terminal display columns, where wide characters take up two.
+    Columns(PositionList),
+    /// Every column of a fixed-width record, from `--widths` or `--spec`.
+    FixedWidth(Vec<FixedColumn>),
+}
+
+/// One column of a fixed-width layout, `width` characters wide.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixedColumn {
+    name: Option<String>,
+    width: usize,
+}
+
+/// A `--spec` file, listing the columns in order:
+///
+/// ```toml
+/// [[columns]]
+/// name = "id"
+/// width = 5
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixedWidthSpec {
+    columns: Vec<FixedColumn>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum QuoteStyle {
+    Always,
+    Necessary,
+    Never,
+}
+
+impl ValueEnum for QuoteStyle {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Always, Self::Necessary, Self::Never]
+    }
+
+    fn to_possible_value(&self) -> Option<builder::PossibleValue> {
+        Some(match self {
+            Self::Always => builder::PossibleValue::new("always"),
+            Self::Necessary => builder::PossibleValue::new("necessary"),
+            Self::Never => builder::PossibleValue::new("never"),
+        })
+    }
+}
+
+/// How `-f` and `-F` selections are written.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum OutputFormat {
+    /// Fields joined with `--output-delimiter`.
+    Delimited,
+    /// A single array of objects keyed by column name.
+    Json,
+    /// One object per line.
+    Ndjson,
+}
+
+impl ValueEnum for OutputFormat {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Delimited, Self::Json, Self::Ndjson]
+    }
+
+    fn to_possible_value(&self) -> Option<builder::PossibleValue> {
+        Some(match self {
+            Self::Delimited => builder::PossibleValue::new("delimited"),
+            Self::Json => builder::PossibleValue::new("json"),
+            Self::Ndjson => builder::PossibleValue::new("ndjson"),
+        })
+    }
+}
+
+impl From<QuoteStyle> for csv::QuoteStyle {
+    fn from(style: QuoteStyle) -> Self {
+        match style {
+            QuoteStyle::Always => csv::QuoteStyle::Always,
+            QuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+            QuoteStyle::Never => csv::QuoteStyle::Never,
+        }
+    }
+}
+
+/// What to do with a CSV record that can't be parsed, or whose length
+/// differs from the first record's.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum ErrorPolicy {
+    Skip,
+    Warn,
+    Fail,
+}
+
+impl ValueEnum for ErrorPolicy {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Skip, Self::Warn, Self::Fail]
+    }
+
+    fn to_possible_value(&self) -> Option<builder::PossibleValue> {
+        Some(match self {
+            Self::Skip => builder::PossibleValue::new("skip"),
+            Self::Warn => builder::PossibleValue::new("warn"),
+            Self::Fail => builder::PossibleValue::new("fail"),
+        })
+    }
+}
+
+/// A `--where` test that a record must pass to be printed.
+#[derive(Debug)]
+struct Condition {
+    column: ColumnRef,
+    operator: Operator,
+    value: String,
+}
+
+/// A column number counted from 1, or a header name.
+#[derive(Debug)]
+enum ColumnRef {
+    Number(usize),
+    Name(String),
+}
+
+#[derive(Debug)]
+enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Matches(Regex),
+    NotMatches(Regex),
+}
+
+impl Condition {
+    /// Compares as numbers when both sides parse as one, else as strings.
+    fn matches(&self, field: &str) -> bool {
+        let ordering = || match (field.parse::<f64>(), self.value.parse::<f64>()) {
+            (Ok(field), Ok(value)) => field.partial_cmp(&value),
+            _ => Some(field.cmp(self.value.as_str())),
+        };
+        match &self.operator {
+            Operator::Eq => ordering() == Some(Ordering::Equal),
+            Operator::Ne => ordering() != Some(Ordering::Equal),
+            Operator::Lt => ordering() == Some(Ordering::Less),
+            Operator::Le => matches!(ordering(), Some(Ordering::Less | Ordering::Equal)),
+            Operator::Gt => ordering() == Some(Ordering::Greater),
+            Operator::Ge => matches!(ordering(), Some(Ordering::Greater | Ordering::Equal)),
+            Operator::Matches(re) => re.is_match(field),
+            Operator::NotMatches(re) => !re.is_match(field),
+        }
+    }
+
+    /// Finds the column this condition tests, by name in `headers` if it
+    /// has one.
+    fn column_index(&self, headers: Option<&StringRecord>) -> MyResult<usize> {
+        match (&self.column, headers) {
+            (ColumnRef::Number(i), _) => Ok(*i),
+            (ColumnRef::Name(name), Some(headers)) => column_index(headers, name),
+            (ColumnRef::Name(name), None) => Err(From::from(format!(
+                "--where column \"{}\" needs a header row, use --header or a number",
+                name
+            ))),
+        }
+    }
+}
+
+/// Parses `COLUMN OP VALUE`, where OP is the first operator in `txt`.
+fn parse_condition(txt: &str) -> MyResult<Condition> {
+    let invalid = |why: String| format!("invalid --where \"{}\": {}", txt, why);
+    let (column, rest) = match txt.find(['=', '!', '<', '>', '~']) {
+        Some(i) if i > 0 => txt.split_at(i),
+        _ => return Err(From::from(invalid("expected COLUMN OP VALUE, like year>1900".to_string()))),
+    };
+    let (operator, value) = ["!=", "!~", "<=", ">=", "=", "<", ">", "~"]
+        .iter()
+        .find_map(|op| rest.strip_prefix(op).map(|value| (*op, value)))
+        .ok_or_else(|| invalid(format!("unknown operator in \"{}\"", rest)))?;
+    let regex = || Regex::new(value).map_err(|e| invalid(e.to_string()));
+    let operator = match operator {
+        "=" => Operator::Eq,
+        "!=" => Operator::Ne,
+        "<" => Operator::Lt,
+        "<=" => Operator::Le,
+        ">" => Operator::Gt,
+        ">=" => Operator::Ge,
+        "~" => Operator::Matches(regex()?),
+        _ => Operator::NotMatches(regex()?),
+    };
+    let column = match usize::from_str(column) {
+        Ok(0) => return Err(From::from(invalid("fields are numbered from 1".to_string()))),
+        Ok(n) => ColumnRef::Number(n - 1),
+        Err(_) => ColumnRef::Name(column.to_string()),
+    };
+    Ok(Condition { column, operator, value: value.to_string() })
+}
+
+/// How a line is split into fields.
+#[derive(Debug)]
+enum FieldSplit {
+    /// CSV parsing with `--delim`.
+    Delimiter,
+    /// Runs of spaces and tabs, ignoring leading and trailing ones.
+    Whitespace,
+    Pattern(Regex),
 }
 
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
     delimiter: u8,
+    split: FieldSplit,
     extract: Extract,
+    header: bool,
+    only_delimited: bool,
+    conditions: Vec<Condition>,
+    on_error: ErrorPolicy,
+    order: ListOrder,
+    output_delimiter: u8,
+    quote_style: QuoteStyle,
+    quote: u8,
+    escape: Option<u8>,
+    tsv_strict: bool,
+    terminator: u8,
+    output: OutputFormat,
+    infer_types: bool,
+    graphemes: bool,
+    no_split: bool,
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -51,7 +285,8 @@ pub fn get_args() -> MyResult<Config> {
             .help("Select bytes")
             .action(ArgAction::Append)
             .value_delimiter(',')
-            .conflicts_with_all(["fields", "chars"])
+            .allow_hyphen_values(true)
+            .conflicts_with_all(["fields", "field_names", "chars", "columns"])
         )
         .arg(
             Arg::new("chars")
@@ -61,7 +296,8 @@ pub fn get_args() -> MyResult<Config> {
             .help("Select characters")
             .action(ArgAction::Append)
             .value_delimiter(',')
-            .conflicts_with_all(["bytes", "fields"])
+            .allow_hyphen_values(true)
+            .conflicts_with_all(["bytes", "fields", "field_names", "columns"])
         )
         .arg(
             Arg::new("fields")
@@ -70,85 +306,498 @@ pub fn get_args() -> MyResult<Config> {
             .value_name("FIELDS")
             .help("Select fields")
             .value_delimiter(',')
-            .conflicts_with_all(["chars", "bytes"])
+            .allow_hyphen_values(true)
+            .conflicts_with_all(["field_names", "chars", "bytes", "columns"])
+        )
+        .arg(
+            Arg::new("field_names")
+            .short('F')
+            .long("field-names")
+            .value_name("NAMES")
+            .help("Select fields by header name, implies --header")
+            .value_delimiter(',')
+            .allow_hyphen_values(true)
+            .conflicts_with_all(["fields", "chars", "bytes", "columns"])
+        )
+        .arg(
+            Arg::new("header")
+            .long("header")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["chars", "bytes", "columns"])
+            .help("Treat the first record as a header row")
+        )
+        .arg(
+            Arg::new("only_delimited")
+            .short('s')
+            .long("only-delimited")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["chars", "bytes", "columns", "widths", "spec"])
+            .help("Skip lines that have no delimiter")
+        )
+        .arg(
+            Arg::new("where")
+            .long("where")
+            .value_name("CONDITION")
+            .action(ArgAction::Append)
+            .conflicts_with_all(["chars", "bytes", "columns"])
+            .help("Only print records where COLUMN OP VALUE holds, like year>1900")
+            .long_help(
+                "Only print records where COLUMN OP VALUE holds, like year>1900. \
+                 COLUMN is a number or a header name, and OP is one of =, !=, <, <=, \
+                 >, >=, ~ (matches a regex) or !~. Values are compared as numbers \
+                 when both sides are numbers. Repeat to require several conditions."
+            )
+        )
+        .arg(
+            Arg::new("on_error")
+            .long("on-error")
+            .value_name("POLICY")
+            .value_parser(value_parser!(ErrorPolicy))
+            .default_value("fail")
+            .conflicts_with_all(["chars", "bytes", "columns", "widths", "spec", "whitespace", "delimiter_regex"])
+            .help("Skip, warn about or fail on CSV records that can't be parsed or have uneven lengths")
+        )
+        .arg(
+            Arg::new("complement")
+            .long("complement")
+            .action(ArgAction::SetTrue)
+            .help("Select everything except the listed positions")
+        )
+        .arg(
+            Arg::new("reorder")
+            .long("reorder")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("complement")
+            .help("Print selections in the order given, repeats included")
+            .long_help(
+                "Print selections in the order given, repeats included, so -f 3,1 \
+                 prints field 3 before field 1 and -f 1,1 prints field 1 twice. \
+                 Without it the list is sorted and merged like GNU cut does."
+            )
+        )
+        .arg(
+            Arg::new("output_delimiter")
+            .long("output-delimiter")
+            .value_name("DELIM")
+            .help("Output field delimiter [default: --delim]")
+        )
+        .arg(
+            Arg::new("quote_style")
+            .long("quote-style")
+            .value_name("STYLE")
+            .value_parser(value_parser!(QuoteStyle))
+            .default_value("necessary")
+            .help("When to quote output fields")
+        )
+        .arg(
+            Arg::new("quote")
+            .long("quote")
+            .value_name("CHAR")
+            .default_value("\"")
+            .help("Quote character for input and output")
+        )
+        .arg(
+            Arg::new("escape")
+            .long("escape")
+            .value_name("CHAR")
+            .help("Escape character for quotes instead of doubling them")
+        )
+        .arg(
+            Arg::new("tsv_strict")
+            .long("tsv-strict")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["output_delimiter", "quote_style"])
+            .help("Write unquoted TSV, failing on fields with tabs or newlines")
+        )
+        .arg(
+            Arg::new("zero_terminated")
+            .short('z')
+            .long("zero-terminated")
+            .action(ArgAction::SetTrue)
+            .help("Lines end in NUL instead of newline, on input and output")
+        )
+        .arg(
+            Arg::new("record_separator")
+            .long("record-separator")
+            .value_name("BYTE")
+            .conflicts_with("zero_terminated")
+            .help("Lines end in BYTE instead of newline, on input and output")
+        )
+        .arg(
+            Arg::new("output")
+            .long("output")
+            .value_name("FORMAT")
+            .value_parser(value_parser!(OutputFormat))
+            .default_value("delimited")
+            .conflicts_with_all(["chars", "bytes", "columns", "tsv_strict"])
+            .help("Write fields as delimited text, a JSON array or NDJSON")
+        )
+        .arg(
+            Arg::new("infer_types")
+            .long("infer-types")
+            .action(ArgAction::SetTrue)
+            .help("Write numbers and booleans as JSON values and empty fields as null")
+        )
+        .arg(
+            Arg::new("whitespace")
+            .short('w')
+            .long("whitespace")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["delimiter", "delimiter_regex", "chars", "bytes", "columns"])
+            .help("Split fields on runs of spaces and tabs")
+        )
+        .arg(
+            Arg::new("delimiter_regex")
+            .short('D')
+            .long("delim-regex")
+            .value_name("REGEX")
+            .conflicts_with_all(["delimiter", "chars", "bytes", "columns"])
+            .help("Split fields on matches of REGEX")
+        )
+        .arg(
+            Arg::new("columns")
+            .long("columns")
+            .value_name("COLUMNS")
+            .help("Select display columns, counting wide characters as 2")
+            .value_delimiter(',')
+            .allow_hyphen_values(true)
+            .conflicts_with_all(["fields", "field_names", "chars", "bytes"])
+        )
+        .arg(
+            Arg::new("graphemes")
+            .long("graphemes")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["fields", "field_names", "bytes", "columns"])
+            .help("Count user-perceived characters (grapheme clusters) with -c")
+        )
+        .arg(
+            Arg::new("no_split")
+            .short('n')
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["fields", "field_names", "chars", "columns"])
+            .help("Don't split multibyte characters with -b")
+        )
+        .arg(
+            Arg::new("widths")
+            .long("widths")
+            .value_name("WIDTHS")
+            .value_parser(value_parser!(u64).range(1..))
+            .value_delimiter(',')
+            .conflicts_with_all(["spec", "fields", "field_names", "chars", "bytes", "columns", "header"])
+            .help("Split lines into fixed-width columns of WIDTHS characters")
+        )
+        .arg(
+            Arg::new("spec")
+            .long("spec")
+            .value_name("FILE")
+            .conflicts_with_all(["fields", "field_names", "chars", "bytes", "columns", "header"])
+            .help("Split lines into the named fixed-width columns listed in a TOML file")
         ).get_matches();
 
-    let extract: Extract = if cmd.get_many::<String>("fields").is_some() {
-        let mut parse_fields = cmd.get_many::<String>("fields").unwrap().map(parse_index);
-        let mut pl: PositionList = vec![];
-        for _i in 0..parse_fields.len() {
-            match parse_fields.next().unwrap() {
-                Ok(range) => { pl.push(range) },
-                Err(err) => { return Err(err); }
-            }
-        }
-        Extract::Fields(pl)
-    } else if cmd.get_many::<String>("chars").is_some() {
-        let mut parse_fields = cmd.get_many::<String>("chars").unwrap().map(parse_index);
-        let mut pl: PositionList = vec![];
-        for _i in 0..parse_fields.len() {
-            match parse_fields.next().unwrap() {
-                Ok(range) => { pl.push(range) },
-                Err(err) => { return Err(err); }
-            }
-        }
-        Extract::Chars(pl)
+    let order = if cmd.get_flag("complement") {
+        ListOrder::Complement
+    } else if cmd.get_flag("reorder") {
+        ListOrder::AsGiven
     } else {
-        let mut parse_fields = cmd.get_many::<String>("bytes").unwrap().map(parse_index);
-        let mut pl: PositionList = vec![];
-        for _i in 0..parse_fields.len() {
-            match parse_fields.next().unwrap() {
-                Ok(range) => { pl.push(range) },
-                Err(err) => { return Err(err); }
-            }
-        }
-        Extract::Bytes(pl)
+        ListOrder::Sorted
+    };
+    let extract: Extract = if let Some(fields) = cmd.get_many::<String>("fields") {
+        Extract::Fields(parse_positions(fields, ListKind::Fields, order)?)
+    } else if let Some(names) = cmd.get_many::<String>("field_names") {
+        Extract::Names(names.map(|e| e.to_string()).collect())
+    } else if let Some(chars) = cmd.get_many::<String>("chars") {
+        Extract::Chars(parse_positions(chars, ListKind::Positions, order)?)
+    } else if let Some(columns) = cmd.get_many::<String>("columns") {
+        Extract::Columns(parse_positions(columns, ListKind::Positions, order)?)
+    } else if let Some(widths) = cmd.get_many::<u64>("widths") {
+        Extract::FixedWidth(widths.map(|&width| FixedColumn { name: None, width: width as usize }).collect())
+    } else if let Some(spec) = cmd.get_one::<String>("spec") {
+        Extract::FixedWidth(read_spec(spec)?)
+    } else if let Some(bytes) = cmd.get_many::<String>("bytes") {
+        Extract::Bytes(parse_positions(bytes, ListKind::Positions, order)?)
+    } else {
+        return Err(From::from(
+            "Must have --fields, --bytes, or --chars, or one of --field-names, --columns, --widths and --spec",
+        ));
     };
 
-
-    let delimiter = cmd.get_one::<String>("delimiter").unwrap();
-    let delim_bytes = delimiter.as_bytes();
-    if delim_bytes.len() != 1 {
-        return Err(From::from(format!("--delim \"{}\" must be a single byte", delimiter)));
+    let delimiter = single_byte("delim", cmd.get_one::<String>("delimiter").unwrap())?;
+    let split = if cmd.get_flag("whitespace") {
+        FieldSplit::Whitespace
+    } else if let Some(pattern) = cmd.get_one::<String>("delimiter_regex") {
+        match Regex::new(pattern) {
+            Ok(re) => FieldSplit::Pattern(re),
+            Err(_) => return Err(From::from(format!("Invalid --delim-regex \"{}\"", pattern))),
+        }
+    } else {
+        FieldSplit::Delimiter
+    };
+    let tsv_strict = cmd.get_flag("tsv_strict");
+    let output_delimiter = match cmd.get_one::<String>("output_delimiter") {
+        Some(output_delimiter) => single_byte("output-delimiter", output_delimiter)?,
+        None if tsv_strict => b'\t',
+        None => match split {
+            FieldSplit::Delimiter => delimiter,
+            FieldSplit::Whitespace | FieldSplit::Pattern(_) => b' ',
+        },
+    };
+    let output = *cmd.get_one::<OutputFormat>("output").unwrap();
+    if cmd.get_flag("infer_types") && output == OutputFormat::Delimited {
+        return Err(From::from("--infer-types needs --output json or ndjson"));
     }
+    let quote_style = if tsv_strict {
+        QuoteStyle::Never
+    } else {
+        *cmd.get_one::<QuoteStyle>("quote_style").unwrap()
+    };
 
     Ok(Config{
         files: cmd.get_many::<String>("files").unwrap().map(|e| e.to_string()).collect(),
-        delimiter: *delim_bytes.first().unwrap(),
+        delimiter,
+        split,
+        header: cmd.get_flag("header") || matches!(extract, Extract::Names(_)),
+        only_delimited: cmd.get_flag("only_delimited"),
+        conditions: cmd
+            .get_many::<String>("where")
+            .unwrap_or_default()
+            .map(|condition| parse_condition(condition))
+            .collect::<MyResult<_>>()?,
+        on_error: *cmd.get_one::<ErrorPolicy>("on_error").unwrap(),
         extract,
+        order,
+        output_delimiter,
+        quote_style,
+        quote: single_byte("quote", cmd.get_one::<String>("quote").unwrap())?,
+        escape: cmd.get_one::<String>("escape").map(|e| single_byte("escape", e)).transpose()?,
+        tsv_strict,
+        terminator: match cmd.get_one::<String>("record_separator") {
+            Some(separator) => single_byte("record-separator", separator)?,
+            None if cmd.get_flag("zero_terminated") => b'\0',
+            None => b'\n',
+        },
+        output,
+        infer_types: cmd.get_flag("infer_types"),
+        graphemes: cmd.get_flag("graphemes"),
+        no_split: cmd.get_flag("no_split"),
     })
 }
 
+fn read_spec(filename: &str) -> MyResult<Vec<FixedColumn>> {
+    let spec: FixedWidthSpec = std::fs::read_to_string(filename)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| toml::from_str(&contents).map_err(|e| e.to_string()))
+        .map_err(|e| format!("{}: {}", filename, e))?;
+    if let Some(column) = spec.columns.iter().find(|column| column.width == 0) {
+        return Err(From::from(format!(
+            "{}: column \"{}\" must be at least 1 character wide",
+            filename,
+            column.name.as_deref().unwrap_or_default()
+        )));
+    }
+    Ok(spec.columns)
+}
+
+fn single_byte(name: &str, value: &str) -> MyResult<u8> {
+    match value.as_bytes() {
+        [byte] => Ok(*byte),
+        _ => Err(From::from(format!("--{} \"{}\" must be a single byte", name, value))),
+    }
+}
+
 pub fn run(config: Config) -> MyResult<()> {
+    let mut out = Output::new(&config);
     for filename in &config.files {
         match open(filename) {
             Err(err) => eprintln!("{}: {}", filename, err),
             Ok(file) => match &config.extract {
-                Extract::Fields(field_pos) => {
-                    let mut reader = ReaderBuilder::new()
-                        .delimiter(config.delimiter)
-                        .has_headers(false)
-                        .from_reader(file);
-                    let mut wtr = WriterBuilder::new()
-                        .delimiter(config.delimiter)
-                        .from_writer(io::stdout());
-                    for record in reader.records() {
-                        let record = record?;
-                        wtr.write_record(extract_fields(
-                            &record,
-                            field_pos
-                        ))?;
-                    }
-                }
-                Extract::Bytes(byte_pos) => {
-                    for line in file.lines() {
-                        println!("{}", extract_bytes(&line?, byte_pos));
-                    }
+                Extract::Fields(_) | Extract::Names(_) | Extract::FixedWidth(_) => {
+                    cut_fields(&config, filename, file, &mut out)?
                 }
+                Extract::Bytes(byte_pos) => cut_bytes(&config, file, byte_pos)?,
                 Extract::Chars(char_pos) => {
-                    for line in file.lines() {
-                        println!("{}", extract_chars(&line?, char_pos));
+                    cut_lines(&config, file, |line| extract_chars(line, char_pos, config.graphemes))?
+                }
+                Extract::Columns(col_pos) => cut_lines(&config, file, |line| extract_columns(line, col_pos))?,
+            }
+        }
+    }
+    out.finish(&config)
+}
+
+type Records<'a> = Box<dyn Iterator<Item = MyResult<(u64, StringRecord)>> + 'a>;
+
+/// Reads `file` as records paired with the line each one starts on,
+/// leaving out the lines `--only-delimited` drops.
+fn records<'a>(config: &'a Config, filename: &'a str, file: Box<dyn BufRead>) -> Records<'a> {
+    if let Extract::FixedWidth(columns) = &config.extract {
+        return Box::new(lines(file, config.terminator).enumerate().map(move |(i, line)| {
+            Ok((i as u64 + 1, split_fixed_width(columns, &line?)))
+        }));
+    }
+    match &config.split {
+        FieldSplit::Delimiter => {
+            let reader = ReaderBuilder::new()
+                .delimiter(config.delimiter)
+                .has_headers(false)
+                .terminator(match config.terminator {
+                    b'\n' => Terminator::CRLF,
+                    terminator => Terminator::Any(terminator),
+                })
+                .flexible(true)
+                .quote(config.quote)
+                .escape(config.escape)
+                .double_quote(config.escape.is_none())
+                .from_reader(file);
+            let mut expected_len = None;
+            Box::new(reader.into_records().filter_map(move |record| {
+                let (pos, problem) = match record {
+                    Ok(record) if config.only_delimited && record.len() < 2 => return None,
+                    Ok(record) if *expected_len.get_or_insert(record.len()) == record.len() => {
+                        let line = record.position().map_or(0, |pos| pos.line());
+                        return Some(Ok((line, record)));
+                    }
+                    Ok(record) => (
+                        record.position().cloned(),
+                        format!("expected {} fields, found {}", expected_len.unwrap_or_default(), record.len()),
+                    ),
+                    Err(e) if matches!(e.kind(), csv::ErrorKind::Io(_)) => return Some(Err(From::from(e))),
+                    Err(e) => (e.position().cloned(), bad_record(&e)),
+                };
+                let message = match pos {
+                    Some(pos) => format!("{}: line {}, byte {}: {}", filename, pos.line(), pos.byte(), problem),
+                    None => format!("{}: {}", filename, problem),
+                };
+                match config.on_error {
+                    ErrorPolicy::Skip => None,
+                    ErrorPolicy::Warn => {
+                        eprintln!("{}", message);
+                        None
                     }
+                    ErrorPolicy::Fail => Some(Err(From::from(message))),
+                }
+            }))
+        }
+        split => Box::new(
+            lines(file, config.terminator)
+                .enumerate()
+                .map(move |(i, line)| Ok((i as u64 + 1, split_line(split, &line?))))
+                .filter(|record| {
+                    !config.only_delimited || !matches!(record, Ok((_, record)) if record.len() < 2)
+                }),
+        ),
+    }
+}
+
+/// Says what's wrong with a CSV record the reader rejected.
+fn bad_record(e: &csv::Error) -> String {
+    match e.kind() {
+        csv::ErrorKind::Utf8 { err, .. } => format!("invalid UTF-8 in field {}", err.field() + 1),
+        _ => e.to_string(),
+    }
+}
+
+fn split_line(split: &FieldSplit, line: &str) -> StringRecord {
+    match split {
+        FieldSplit::Whitespace => line.split([' ', '\t']).filter(|field| !field.is_empty()).collect(),
+        FieldSplit::Pattern(re) => re.split(line).collect(),
+        FieldSplit::Delimiter => unreachable!(),
+    }
+}
+
+/// Slices `line` into `columns` by character count, trimming the padding
+/// around each value. Columns past the end of a short line are empty.
+fn split_fixed_width(columns: &[FixedColumn], line: &str) -> StringRecord {
+    let mut rest = line;
+    columns
+        .iter()
+        .map(|column| {
+            let end = rest.char_indices().nth(column.width).map_or(rest.len(), |(i, _)| i);
+            let (value, tail) = rest.split_at(end);
+            rest = tail;
+            value.trim()
+        })
+        .collect()
+}
+
+fn cut_fields(
+    config: &Config,
+    filename: &str,
+    file: Box<dyn BufRead>,
+    out: &mut Output,
+) -> MyResult<()> {
+    let mut records = records(config, filename, file);
+    let headers = match &config.extract {
+        Extract::FixedWidth(columns) if columns.iter().all(|column| column.name.is_some()) => {
+            Some(columns.iter().filter_map(|column| column.name.as_deref()).collect())
+        }
+        Extract::FixedWidth(_) => None,
+        _ => match records.next() {
+            None => return Ok(()),
+            Some(first) if config.header => Some(first?.1),
+            Some(first) => {
+                records = Box::new(std::iter::once(first).chain(records));
+                None
+            }
+        },
+    };
+    let conditions = config
+        .conditions
+        .iter()
+        .map(|condition| Ok((condition.column_index(headers.as_ref())?, condition)))
+        .collect::<MyResult<Vec<_>>>()
+        .map_err(|e| format!("{}: {}", filename, e))?;
+    let keep = |record: &StringRecord| {
+        conditions.iter().all(|(i, condition)| record.get(*i).is_some_and(|field| condition.matches(field)))
+    };
+    let field_pos = match (&config.extract, &headers) {
+        (Extract::Names(names), Some(headers)) => resolve_names(names, headers, config.order)
+            .map_err(|e| format!("{}: {}", filename, e))?,
+        (Extract::Fields(field_pos), _) => field_pos.clone(),
+        (Extract::FixedWidth(columns), _) => vec![Range { start: 0, end: columns.len() }],
+        _ => unreachable!(),
+    };
+    match (out, &headers) {
+        (Output::Delimited(wtr), headers) => {
+            if let Some(headers) = headers {
+                write_fields(config, wtr, headers, &field_pos)
+                    .map_err(|e| format!("{}: line 1: {}", filename, e))?;
+            }
+            for record in records {
+                let (line, record) = record?;
+                if !keep(&record) {
+                    continue;
+                }
+                write_fields(config, wtr, &record, &field_pos)
+                    .map_err(|e| format!("{}: line {}: {}", filename, line, e))?;
+            }
+        }
+        (Output::Json(_), None) => {
+            return Err(From::from(format!(
+                "{}: --output {} needs column names from --header, -F or a --spec naming every column",
+                filename,
+                config.output.to_possible_value().unwrap().get_name()
+            )))
+        }
+        (Output::Json(json), Some(headers)) => {
+            // An object can only hold each key once.
+            let mut keys = HashSet::new();
+            if let Some(key) = field_pos
+                .iter()
+                .flat_map(|range| clamp(range, headers.len()))
+                .map(|i| &headers[i])
+                .find(|&key| !keys.insert(key))
+            {
+                return Err(From::from(format!(
+                    "{}: --output {} can't hold column \"{}\" more than once",
+                    filename,
+                    config.output.to_possible_value().unwrap().get_name(),
+                    key
+                )));
+            }
+            for record in records {
+                let (_, record) = record?;
+                if keep(&record) {
+                    json.write_row(config, headers, &record, &field_pos)?;
                 }
             }
         }
@@ -156,15 +805,198 @@ pub fn run(config: Config) -> MyResult<()> {
     Ok(())
 }
 
-fn parse_index(txt: &String) -> Result<Range<usize>, Box<dyn Error>> {
-    let range_regx = Regex::new(r"^(?<start>\d+)(\-)*(?<end>\d+)*$").unwrap();
-    if !range_regx.is_match(txt) {
-        return Err(From::from(format!("Invalid range \"{}\"", txt)));
+/// Where `cut_fields` writes, kept across files so `--output json` prints
+/// one array for all of them.
+enum Output {
+    Delimited(Box<csv::Writer<io::Stdout>>),
+    Json(JsonWriter),
+}
+
+impl Output {
+    fn new(config: &Config) -> Self {
+        match config.output {
+            OutputFormat::Delimited => Output::Delimited(Box::new(
+                WriterBuilder::new()
+                    .delimiter(config.output_delimiter)
+                    .quote_style(config.quote_style.into())
+                    .quote(config.quote)
+                    .escape(config.escape.unwrap_or(b'\\'))
+                    .double_quote(config.escape.is_none())
+                    .flexible(true)
+                    .terminator(Terminator::Any(config.terminator))
+                    .from_writer(io::stdout()),
+            )),
+            OutputFormat::Json | OutputFormat::Ndjson => Output::Json(JsonWriter {
+                out: io::BufWriter::new(io::stdout()),
+                rows: 0,
+            }),
+        }
+    }
+
+    fn finish(self, config: &Config) -> MyResult<()> {
+        match self {
+            Output::Delimited(mut wtr) => wtr.flush()?,
+            Output::Json(mut json) => {
+                if config.output == OutputFormat::Json {
+                    json.out.write_all(if json.rows == 0 { b"[]\n" } else { b"\n]\n" })?;
+                }
+                json.out.flush()?
+            }
+        }
+        Ok(())
+    }
+}
+
+struct JsonWriter {
+    out: io::BufWriter<io::Stdout>,
+    rows: u64,
+}
+
+impl JsonWriter {
+    /// Writes the selected fields of `record` as an object keyed by the
+    /// matching `headers`. Fields missing from a short record are null.
+    fn write_row(
+        &mut self,
+        config: &Config,
+        headers: &StringRecord,
+        record: &StringRecord,
+        field_pos: &[Range<usize>],
+    ) -> MyResult<()> {
+        let row: serde_json::Map<String, serde_json::Value> = field_pos
+            .iter()
+            .flat_map(|range| clamp(range, headers.len()))
+            .map(|i| {
+                let value = match record.get(i) {
+                    None => serde_json::Value::Null,
+                    Some(field) if config.infer_types => infer_type(field),
+                    Some(field) => serde_json::Value::from(field),
+                };
+                (headers[i].to_string(), value)
+            })
+            .collect();
+        if config.output == OutputFormat::Json {
+            self.out.write_all(if self.rows == 0 { b"[\n" } else { b",\n" })?;
+        }
+        serde_json::to_writer(&mut self.out, &row)?;
+        if config.output == OutputFormat::Ndjson {
+            self.out.write_all(b"\n")?;
+        }
+        self.rows += 1;
+        Ok(())
+    }
+}
+
+/// Reads `true`, `false` and numbers as themselves and empty fields as
+/// null. A number is only kept when it prints back as the same text, so
+/// leading zeros like in zip codes, and integers too big for 64 bits like
+/// long IDs, stay strings rather than lose digits.
+fn infer_type(field: &str) -> serde_json::Value {
+    let number = if let Ok(n) = field.parse::<i64>() {
+        Some(serde_json::Number::from(n))
+    } else if let Ok(n) = field.parse::<u64>() {
+        Some(serde_json::Number::from(n))
+    } else {
+        field.parse::<f64>().ok().and_then(serde_json::Number::from_f64)
+    };
+    match field {
+        "" => serde_json::Value::Null,
+        "true" => serde_json::Value::Bool(true),
+        "false" => serde_json::Value::Bool(false),
+        _ => match number {
+            Some(number) if number.to_string() == field => serde_json::Value::Number(number),
+            _ => serde_json::Value::from(field),
+        },
+    }
+}
+
+fn write_fields<W: io::Write>(
+    config: &Config,
+    wtr: &mut csv::Writer<W>,
+    record: &StringRecord,
+    field_pos: &[Range<usize>],
+) -> MyResult<()> {
+    let fields = extract_fields(record, field_pos);
+    if config.tsv_strict && fields.iter().any(|field| field.contains(['\t', '\n', '\r'])) {
+        return Err(From::from("--tsv-strict fields cannot contain tabs or newlines"));
+    }
+    wtr.write_record(fields)?;
+    Ok(())
+}
+
+/// Which kind of list is being parsed, for GNU-style error messages.
+#[derive(Debug, Clone, Copy)]
+enum ListKind {
+    Fields,
+    Positions,
+}
+
+impl ListKind {
+    fn message(self, e: SelectorError) -> String {
+        match (self, e) {
+            // GNU cut says the same about an empty item.
+            (ListKind::Fields, SelectorError::Zero | SelectorError::Empty) => {
+                "fields are numbered from 1".to_string()
+            }
+            (ListKind::Positions, SelectorError::Zero | SelectorError::Empty) => {
+                "byte/character positions are numbered from 1".to_string()
+            }
+            (ListKind::Fields, SelectorError::InvalidValue(txt)) => format!("invalid field value '{}'", txt),
+            (ListKind::Positions, SelectorError::InvalidValue(txt)) => {
+                format!("invalid byte/character position '{}'", txt)
+            }
+            (_, e) => e.to_string(),
+        }
+    }
+}
+
+/// Parses the comma-separated items of a list into ranges ordered as
+/// `order` asks for.
+fn parse_positions<'a>(
+    items: impl Iterator<Item = &'a String>,
+    kind: ListKind,
+    order: ListOrder,
+) -> MyResult<PositionList> {
+    match Selector::parse_items(items.map(String::as_str), order) {
+        Ok(selector) => Ok(selector.into_ranges()),
+        Err(e) => Err(From::from(kind.message(e))),
+    }
+}
+
+/// Resolves `-F` names against a header row. An item that isn't a column
+/// name itself is tried as a `first-last` range of names, where either end
+/// may be left open like in numeric lists.
+fn resolve_names(names: &[String], headers: &StringRecord, order: ListOrder) -> MyResult<PositionList> {
+    let position = |name: &str| column_index(headers, name);
+    let mut ranges: PositionList = vec![];
+    for name in names {
+        if let Ok(i) = position(name) {
+            ranges.push(i..i + 1);
+            continue;
+        }
+        let (start, end) = match name.split_once('-') {
+            Some((start, end)) if !(start.is_empty() && end.is_empty()) => {
+                let start = if start.is_empty() { 0 } else { position(start)? };
+                let end = if end.is_empty() { usize::MAX } else { position(end)? + 1 };
+                (start, end)
+            }
+            _ => return Err(position(name).unwrap_err()),
+        };
+        if end <= start {
+            return Err(From::from("invalid decreasing range"));
+        }
+        ranges.push(start..end);
     }
-    let caps = range_regx.captures(txt).unwrap();
-    let start: usize = usize::from_str(&caps["start"]).unwrap() - 1;
-    let end: usize = caps.name("end").map_or(start+1, |v| usize::from_str(v.into()).unwrap() + 1).to_owned();
-    Ok(Range{ start, end })
+    Ok(normalize(ranges, order))
+}
+
+fn column_index(headers: &StringRecord, name: &str) -> MyResult<usize> {
+    headers.iter().position(|header| header == name).ok_or_else(|| {
+        From::from(format!(
+            "unknown column \"{}\", available columns: {}",
+            name,
+            headers.iter().collect::<Vec<_>>().join(", ")
+        ))
+    })
 }
 
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
@@ -174,33 +1006,63 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     }
 }
 
-fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String {
-    let chars: Vec<_> = line.chars().collect();
-    let mut selected: Vec<char> = vec![];
+/// Selects the grapheme clusters whose display columns all fall inside a
+/// range, so a wide character cut in half by a range is left out.
+fn extract_columns(line: &str, col_pos: &[Range<usize>]) -> String {
+    let mut columns = vec![];
+    let mut col = 0;
+    for grapheme in line.graphemes(true) {
+        let width = grapheme.width();
+        columns.push((col..col + width.max(1), grapheme));
+        col += width;
+    }
+    col_pos
+        .iter()
+        .flat_map(|range| {
+            columns
+                .iter()
+                .filter(|(cols, _)| range.start <= cols.start && cols.end <= range.end)
+                .map(|(_, grapheme)| *grapheme)
+        })
+        .collect()
+}
 
-    for range in char_pos.iter().cloned() {
-        selected.extend(range.filter_map(|i| chars.get(i)))
+/// Prints what `extract` keeps of each line of `file`.
+fn cut_lines(config: &Config, file: Box<dyn BufRead>, extract: impl Fn(&str) -> String) -> MyResult<()> {
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for line in lines(file, config.terminator) {
+        out.write_all(extract(&line?).as_bytes())?;
+        out.write_all(&[config.terminator])?;
     }
-    selected.iter().collect()
+    out.flush()?;
+    Ok(())
 }
 
-fn extract_bytes(line: &str, byte_pos: &[Range<usize>]) -> String {
-    let bytes = line.as_bytes();
-    let selected: Vec<_> = byte_pos
-        .iter()
-        .cloned()
-        .flat_map(|range| range.filter_map(|i| bytes.get(i).copied()))
-        .collect();
-    String::from_utf8_lossy(&selected).into_owned()
+/// Splits `file` into lines ending in `terminator`. Like `BufRead::lines`,
+/// a `\r` before a newline terminator is dropped too.
+fn lines(file: Box<dyn BufRead>, terminator: u8) -> impl Iterator<Item = MyResult<String>> {
+    file.split(terminator).map(move |line| {
+        let mut line = line?;
+        if terminator == b'\n' && line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Ok(String::from_utf8(line)?)
+    })
 }
 
-fn extract_fields<'a>(
-    records: &'a StringRecord,
-    field_pos: &[Range<usize>]
-) -> Vec<&'a str> {
-    field_pos
-        .iter()
-        .cloned()
-        .flat_map(|range| range.filter_map(|i| records.get(i)))
-        .collect()
+/// Copies the selected bytes of each line to stdout without decoding
+/// them, so input that isn't UTF-8 passes through unchanged.
+fn cut_bytes(config: &Config, mut file: Box<dyn BufRead>, byte_pos: &[Range<usize>]) -> MyResult<()> {
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let mut line = vec![];
+    while file.read_until(config.terminator, &mut line)? > 0 {
+        if line.last() == Some(&config.terminator) {
+            line.pop();
+        }
+        out.write_all(&extract_bytes(&line, byte_pos, config.no_split))?;
+        out.write_all(&[config.terminator])?;
+        line.clear();
+    }
+    out.flush()?;
+    Ok(())
 }
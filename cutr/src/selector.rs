@@ -0,0 +1,255 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use csv::StringRecord;
+use unicode_segmentation::UnicodeSegmentation;
+
+pub(crate) type PositionList = Vec<Range<usize>>;
+
+/// Why a list of positions couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectorError {
+    /// A position of 0.
+    Zero,
+    /// An empty item, like the one between the commas in `1,,3`.
+    Empty,
+    /// An item that isn't a number or a range, like the `x` in `1-x`.
+    InvalidValue(String),
+    /// A range that ends before it starts, like `3-1`.
+    DecreasingRange,
+    /// A lone `-`.
+    NoEndpoint,
+}
+
+impl fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectorError::Zero => write!(f, "positions are numbered from 1"),
+            SelectorError::Empty => write!(f, "empty item in list"),
+            SelectorError::InvalidValue(txt) => write!(f, "invalid position '{}'", txt),
+            SelectorError::DecreasingRange => write!(f, "invalid decreasing range"),
+            SelectorError::NoEndpoint => write!(f, "invalid range with no endpoint: -"),
+        }
+    }
+}
+
+impl Error for SelectorError {}
+
+/// A list of positions like `1,3-5,7-`, numbered from 1, with the same
+/// meaning as in `cut -b`, `-c` and `-f`.
+///
+/// ```
+/// use cutr::Selector;
+///
+/// let selector: Selector = "3,1,5-".parse()?;
+/// assert_eq!(selector.chars("abcdefg"), "acefg");
+/// assert_eq!(selector.bytes(b"abc"), b"ac");
+/// assert_eq!(Selector::parse_in_order("3,1")?.chars("abc"), "ca");
+/// assert!(Selector::parse("3-1").is_err());
+/// # Ok::<(), cutr::SelectorError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    ranges: PositionList,
+}
+
+impl Selector {
+    /// Parses a comma-separated list, sorted and merged like GNU cut
+    /// does, so `3,1` selects 1 then 3 and `1,1` selects 1 once.
+    pub fn parse(spec: &str) -> Result<Self, SelectorError> {
+        Self::parse_items(spec.split(','), ListOrder::Sorted)
+    }
+
+    /// Parses a comma-separated list, keeping the order and repeats given.
+    pub fn parse_in_order(spec: &str) -> Result<Self, SelectorError> {
+        Self::parse_items(spec.split(','), ListOrder::AsGiven)
+    }
+
+    pub(crate) fn parse_items<'a>(
+        items: impl Iterator<Item = &'a str>,
+        order: ListOrder,
+    ) -> Result<Self, SelectorError> {
+        let ranges = items.map(parse_index).collect::<Result<PositionList, _>>()?;
+        Ok(Selector { ranges: normalize(ranges, order) })
+    }
+
+    /// Selects everything this selector leaves out.
+    pub fn complement(&self) -> Self {
+        Selector { ranges: normalize(self.ranges.clone(), ListOrder::Complement) }
+    }
+
+    /// The zero-based ranges selected, in order. Open-ended ranges run up
+    /// to `usize::MAX`.
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+
+    pub(crate) fn into_ranges(self) -> PositionList {
+        self.ranges
+    }
+
+    /// Selects bytes of `line`, even when that splits a character, like
+    /// `cut -b` does.
+    pub fn bytes(&self, line: &[u8]) -> Vec<u8> {
+        extract_bytes(line, &self.ranges, false)
+    }
+
+    /// Selects characters (Unicode scalar values) of `line`.
+    pub fn chars(&self, line: &str) -> String {
+        extract_chars(line, &self.ranges, false)
+    }
+
+    /// Selects fields of `record`, skipping positions past its end.
+    pub fn fields<'a>(&self, record: &'a StringRecord) -> Vec<&'a str> {
+        extract_fields(record, &self.ranges)
+    }
+}
+
+impl FromStr for Selector {
+    type Err = SelectorError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        Selector::parse(spec)
+    }
+}
+
+/// How a parsed list is turned into the ranges to print.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ListOrder {
+    /// Sorted, with overlapping ranges merged.
+    Sorted,
+    /// The gaps between the listed ranges.
+    Complement,
+    /// As listed, repeats included.
+    AsGiven,
+}
+
+/// Sorts and merges `ranges`, returning the gaps between them for
+/// `ListOrder::Complement`, or leaves them alone for `ListOrder::AsGiven`.
+pub(crate) fn normalize(mut ranges: PositionList, order: ListOrder) -> PositionList {
+    if let ListOrder::AsGiven = order {
+        return ranges;
+    }
+    ranges.sort_by_key(|range| range.start);
+
+    let mut merged: PositionList = vec![];
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    if let ListOrder::Sorted = order {
+        return merged;
+    }
+
+    let mut gaps: PositionList = vec![];
+    let mut start = 0;
+    for range in merged {
+        if range.start > start {
+            gaps.push(start..range.start);
+        }
+        start = range.end;
+    }
+    if start < usize::MAX {
+        gaps.push(start..usize::MAX);
+    }
+    gaps
+}
+
+/// Parses `N`, `N-M`, `N-` or `-M` into a zero-based range. Open-ended
+/// ranges run up to `usize::MAX` and are clamped to the line when used.
+fn parse_index(txt: &str) -> Result<Range<usize>, SelectorError> {
+    let parse_number = |num: &str| -> Result<usize, SelectorError> {
+        if num.is_empty() || !num.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(SelectorError::InvalidValue(num.to_string()));
+        }
+        match usize::from_str(num) {
+            Ok(0) => Err(SelectorError::Zero),
+            Ok(n) => Ok(n),
+            Err(_) => Err(SelectorError::InvalidValue(num.to_string())),
+        }
+    };
+    let (start, end) = match txt.split_once('-') {
+        None if txt.is_empty() => return Err(SelectorError::Empty),
+        None => {
+            let n = parse_number(txt)?;
+            (n, Some(n))
+        }
+        Some(("", "")) => return Err(SelectorError::NoEndpoint),
+        Some(("", "0")) => return Err(SelectorError::DecreasingRange),
+        Some(("", end)) => (1, Some(parse_number(end)?)),
+        Some((start, "")) => (parse_number(start)?, None),
+        Some((start, end)) => (parse_number(start)?, Some(parse_number(end)?)),
+    };
+    match end {
+        Some(end) if end < start => Err(SelectorError::DecreasingRange),
+        Some(end) => Ok(start - 1..end),
+        None => Ok(start - 1..usize::MAX),
+    }
+}
+
+/// Limits a possibly open-ended range to a line of `len` items.
+pub(crate) fn clamp(range: &Range<usize>, len: usize) -> Range<usize> {
+    let end = range.end.min(len);
+    range.start.min(end)..end
+}
+
+pub(crate) fn extract_chars(line: &str, char_pos: &[Range<usize>], graphemes: bool) -> String {
+    let chars: Vec<&str> = if graphemes {
+        line.graphemes(true).collect()
+    } else {
+        line.char_indices().map(|(i, c)| &line[i..i + c.len_utf8()]).collect()
+    };
+    char_pos
+        .iter()
+        .flat_map(|range| &chars[clamp(range, chars.len())])
+        .copied()
+        .collect()
+}
+
+/// With `no_split`, a multibyte UTF-8 character is only kept when all of
+/// its bytes are selected. Bytes that aren't valid UTF-8 count as
+/// characters of their own.
+pub(crate) fn extract_bytes(line: &[u8], byte_pos: &[Range<usize>], no_split: bool) -> Vec<u8> {
+    if !no_split {
+        return byte_pos
+            .iter()
+            .flat_map(|range| &line[clamp(range, line.len())])
+            .copied()
+            .collect();
+    }
+    let mut chars: Vec<Range<usize>> = vec![];
+    let mut start = 0;
+    for chunk in line.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            chars.push(start..start + c.len_utf8());
+            start += c.len_utf8();
+        }
+        for _ in chunk.invalid() {
+            chars.push(start..start + 1);
+            start += 1;
+        }
+    }
+    byte_pos
+        .iter()
+        .flat_map(|range| {
+            chars
+                .iter()
+                .filter(|c| range.start <= c.start && c.end <= range.end)
+                .flat_map(|c| &line[c.clone()])
+        })
+        .copied()
+        .collect()
+}
+
+pub(crate) fn extract_fields<'a>(
+    records: &'a StringRecord,
+    field_pos: &[Range<usize>]
+) -> Vec<&'a str> {
+    field_pos
+        .iter()
+        .flat_map(|range| clamp(range, records.len()).filter_map(|i| records.get(i)))
+        .collect()
+}
@@ -35,7 +35,7 @@ fn skips_bad_file() -> TestResult {
     let bad = gen_bad_file();
     let expected = format!("{}: .* [(]os error 2[)]", bad);
     Command::cargo_bin(PRG)?
-        .args(&["-f", "1", CSV, &bad, TSV])
+        .args(["-f", "1", CSV, &bad, TSV])
         .assert()
         .success()
         .stderr(predicate::str::is_match(expected)?);
@@ -58,13 +58,19 @@ fn dies_not_enough_args() -> TestResult {
     dies(&[CSV], "Must have --fields, --bytes, or --chars")
 }
 
+// --------------------------------------------------
+#[test]
+fn dies_header_without_list() -> TestResult {
+    dies(&["--header", CSV], "Must have --fields, --bytes, or --chars")
+}
+
 // --------------------------------------------------
 #[test]
 fn dies_bad_digit_field() -> TestResult {
     let bad = random_string();
     dies(
         &[CSV, "-f", &bad],
-        &format!("illegal list value: \"{}\"", &bad),
+        &format!("invalid field value '{}'", &bad),
     )
 }
 
@@ -74,7 +80,7 @@ fn dies_bad_digit_bytes() -> TestResult {
     let bad = random_string();
     dies(
         &[CSV, "-b", &bad],
-        &format!("illegal list value: \"{}\"", &bad),
+        &format!("invalid byte/character position '{}'", &bad),
     )
 }
 
@@ -84,7 +90,7 @@ fn dies_bad_digit_chars() -> TestResult {
     let bad = random_string();
     dies(
         &[CSV, "-c", &bad],
-        &format!("illegal list value: \"{}\"", &bad),
+        &format!("invalid byte/character position '{}'", &bad),
     )
 }
 
@@ -110,7 +116,7 @@ fn dies_bad_delimiter() -> TestResult {
 #[test]
 fn dies_chars_bytes_fields() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&[CSV, "-c", "1", "-f", "1", "-b", "1"])
+        .args([CSV, "-c", "1", "-f", "1", "-b", "1"])
         .assert()
         .failure();
     Ok(())
@@ -120,7 +126,7 @@ fn dies_chars_bytes_fields() -> TestResult {
 #[test]
 fn dies_bytes_fields() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&[CSV, "-f", "1", "-b", "1"])
+        .args([CSV, "-f", "1", "-b", "1"])
         .assert()
         .failure();
     Ok(())
@@ -130,7 +136,7 @@ fn dies_bytes_fields() -> TestResult {
 #[test]
 fn dies_chars_fields() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&[CSV, "-c", "1", "-f", "1"])
+        .args([CSV, "-c", "1", "-f", "1"])
         .assert()
         .failure();
     Ok(())
@@ -140,7 +146,7 @@ fn dies_chars_fields() -> TestResult {
 #[test]
 fn dies_chars_bytes() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&[CSV, "-c", "1", "-b", "1"])
+        .args([CSV, "-c", "1", "-b", "1"])
         .assert()
         .failure();
     Ok(())
@@ -335,5 +341,409 @@ fn tsv_c1_8() -> TestResult {
 // --------------------------------------------------
 #[test]
 fn repeated_value() -> TestResult {
-    run(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1,1.out")
+    run(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1.out")?;
+    run(&[BOOKS, "-c", "1,1", "--reorder"], "tests/expected/books.c1,1.out")
+}
+
+// --------------------------------------------------
+fn run_stdin(args: &[&str], input: &str, expected: &str) -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected.to_string());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn open_ended_ranges() -> TestResult {
+    run_stdin(&["-c", "4-"], "abcdef\n", "def\n")?;
+    run_stdin(&["-c", "-3"], "abcdef\n", "abc\n")?;
+    run_stdin(&["-b", "-2,5-"], "abcdef\n", "abef\n")?;
+    run_stdin(&["-f", "2-"], "a\tb\tc\n", "b\tc\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn merges_overlapping_ranges() -> TestResult {
+    run_stdin(&["-f", "1-3,2-4"], "a\tb\tc\td\te\n", "a\tb\tc\td\n")?;
+    run_stdin(&["-f", "3,1"], "a\tb\tc\td\te\n", "a\tc\n")?;
+    run_stdin(&["-c", "2,1-2,3-"], "abcdef\n", "abcdef\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn complement() -> TestResult {
+    run_stdin(&["--complement", "-f", "2-3"], "a\tb\tc\td\te\n", "a\td\te\n")?;
+    run_stdin(&["--complement", "-c", "-2,5"], "abcdef\n", "cdf\n")?;
+    run_stdin(&["--complement", "-b", "1-"], "abcdef\n", "\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_invalid_ranges() -> TestResult {
+    dies(&[CSV, "-f", "0"], "fields are numbered from 1")?;
+    dies(&[CSV, "-f", "0-2"], "fields are numbered from 1")?;
+    dies(&[CSV, "-c", "0"], "byte/character positions are numbered from 1")?;
+    dies(&[CSV, "-f", "3-1"], "invalid decreasing range")?;
+    dies(&[CSV, "-b", "-0"], "invalid decreasing range")?;
+    dies(&[CSV, "-f", "-"], "invalid range with no endpoint: -")?;
+    dies(&[CSV, "-f", "1-x"], "invalid field value 'x'")
+}
+
+// --------------------------------------------------
+fn run_output(args: &[&str], expected: &str) -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected.to_string());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_field_names() -> TestResult {
+    run_output(
+        &[CSV, "-d", ",", "-F", "director,title"],
+        "title,director\nThe Blues Brothers,John Landis\nLes Misérables,Tom Hooper\n",
+    )?;
+    run_output(
+        &[CSV, "-d", ",", "-F", "year-director"],
+        "year,director\n1980,John Landis\n2012,Tom Hooper\n",
+    )?;
+    run_output(
+        &[CSV, "-d", ",", "--complement", "-F", "-year"],
+        "director\nJohn Landis\nTom Hooper\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_header_fields() -> TestResult {
+    run_output(
+        &[CSV, "-d", ",", "--header", "-f", "2"],
+        "year\n1980\n2012\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_unknown_field_name() -> TestResult {
+    dies(
+        &[CSV, "-d", ",", "-F", "title,author"],
+        "unknown column \"author\", available columns: title, year, director",
+    )?;
+    dies(&[CSV, "-d", ",", "-F", "director-title"], "invalid decreasing range")
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_to_tsv_strict() -> TestResult {
+    run_output(
+        &["tests/inputs/books.csv", "-d", ",", "-f", "3,1", "--tsv-strict"],
+        "Author\tTitle\nÉmile Zola\tLa Confession de Claude\n\
+         Samuel Beckett\tWaiting for Godot\n\
+         Jules Verne\t20,000 Leagues Under the Sea\n",
+    )?;
+    Command::cargo_bin(PRG)?
+        .args(["-d", ",", "-f", "2", "--tsv-strict"])
+        .write_stdin("a,\"b\tc\"\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "-: line 1: --tsv-strict fields cannot contain tabs or newlines",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn output_delimiter_and_quoting() -> TestResult {
+    run_stdin(
+        &["-d", ",", "-f", "1,3", "--output-delimiter", ";", "--quote-style", "always"],
+        "a,b,\"c,d\"\n",
+        "\"a\";\"c,d\"\n",
+    )?;
+    run_stdin(
+        &["-d", ",", "-f", "2", "--quote-style", "never"],
+        "a,\"b,c\"\n",
+        "b,c\n",
+    )?;
+    run_stdin(
+        &["-d", ",", "-f", "2", "--quote", "'", "--output-delimiter", "|"],
+        "a,'b,c'\n",
+        "b,c\n",
+    )?;
+    run_stdin(&["-d", ",", "-f", "2", "--escape", "\\"], "a,\"b\\\"c\"\n", "\"b\\\"c\"\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_output_delimiter() -> TestResult {
+    dies(
+        &[CSV, "-f", "1", "--output-delimiter", ",,"],
+        "--output-delimiter \",,\" must be a single byte",
+    )?;
+    dies(&[CSV, "-f", "1", "--quote-style", "sometimes"], "invalid value 'sometimes'")
+}
+
+// --------------------------------------------------
+#[test]
+fn whitespace_fields() -> TestResult {
+    let ps = "  PID TTY          TIME CMD\n    1 ?        00:00:01 init\n   42 pts/0\t00:00:00 bash -l\n";
+    run_stdin(&["-w", "-f", "1,4-"], ps, "PID CMD\n1 init\n42 bash -l\n")?;
+    run_stdin(&["-w", "-F", "PID,TIME"], ps, "PID TIME\n1 00:00:01\n42 00:00:00\n")?;
+    run_stdin(&["-w", "-f", "2", "--output-delimiter", ","], ps, "TTY\n?\npts/0\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn regex_fields() -> TestResult {
+    run_stdin(&["-D", ":+", "-f", "2-"], "a::b:c\n", "b c\n")?;
+    run_stdin(
+        &["-D", "[0-9]+", "-f", "1,3", "--output-delimiter", ","],
+        "a1b22c\n",
+        "a,c\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_delimiter_regex() -> TestResult {
+    dies(&[CSV, "-f", "1", "-D", "("], "Invalid --delim-regex \"(\"")?;
+    dies(&[CSV, "-f", "1", "-w", "-d", ","], "cannot be used with")
+}
+
+// --------------------------------------------------
+#[test]
+fn chars_graphemes() -> TestResult {
+    let line = "e\u{301}a\u{1F44D}\u{1F3FD}b\n";
+    run_stdin(&["-c", "1,3"], line, "ea\n")?;
+    run_stdin(&["-c", "1,3", "--graphemes"], line, "e\u{301}\u{1F44D}\u{1F3FD}\n")?;
+    run_stdin(&["-c", "2-", "--graphemes"], line, "a\u{1F44D}\u{1F3FD}b\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn display_columns() -> TestResult {
+    run_stdin(&["--columns", "1-4"], "日本語abc\n", "日本\n")?;
+    run_stdin(&["--columns", "2-5"], "日本語abc\n", "本\n")?;
+    run_stdin(&["--columns", "7-"], "日本語abc\n", "abc\n")?;
+    run_stdin(&["--complement", "--columns", "3-4"], "日本語abc\n", "日語abc\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_graphemes_without_chars() -> TestResult {
+    dies(&[CSV, "-f", "1", "--graphemes"], "cannot be used with")
+}
+
+// --------------------------------------------------
+fn run_bytes(args: &[&str], input: &[u8], expected: &[u8]) -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected.to_vec());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_raw() -> TestResult {
+    let latin1 = b"caf\xe9 \xe9t\xe9\r\nna\xefve\n";
+    run_bytes(&["-b", "3-4"], latin1, b"f\xe9\n\xefv\n")?;
+    run_bytes(&["-b", "1-"], latin1, latin1)?;
+    run_bytes(&["-b", "4"], "café\n".as_bytes(), b"\xc3\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_no_split() -> TestResult {
+    let utf8 = "café été\n".as_bytes();
+    run_bytes(&["-b", "4", "-n"], utf8, b"\n")?;
+    run_bytes(&["-b", "4-5", "-n"], utf8, "é\n".as_bytes())?;
+    run_bytes(&["-b", "5-", "-n"], utf8, " été\n".as_bytes())?;
+    run_bytes(&["-b", "1-4", "-n"], b"ab\xe9c\n", b"ab\xe9c\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn reorder() -> TestResult {
+    run_stdin(&["-f", "3,1", "--reorder"], "a\tb\tc\n", "c\ta\n")?;
+    run_stdin(&["-f", "2,1-2", "--reorder"], "a\tb\tc\n", "b\ta\tb\n")?;
+    run_stdin(&["-c", "3,1,1", "--reorder"], "abc\n", "caa\n")?;
+    run_stdin(&["-b", "2-,1", "--reorder"], "abc\n", "bca\n")?;
+    run_output(
+        &[CSV, "-d", ",", "-F", "director,title", "--reorder"],
+        "director,title\nJohn Landis,The Blues Brothers\nTom Hooper,Les Misérables\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_reorder_complement() -> TestResult {
+    dies(&[CSV, "-f", "1", "--reorder", "--complement"], "cannot be used with")
+}
+
+// --------------------------------------------------
+#[test]
+fn fixed_width() -> TestResult {
+    let fixed = "tests/inputs/books.fixed";
+    run_output(
+        &[fixed, "--spec", "tests/inputs/books.toml"],
+        "last\tfirst\tyear\nZola\tÉmile\t1865\nBeckett\tSamuel\t1952\nVerne\tJules\t1870\n",
+    )?;
+    run_output(
+        &[fixed, "--widths", "10,6", "--output-delimiter", ","],
+        "Zola,Émile\nBeckett,Samuel\nVerne,Jules\n",
+    )?;
+    run_stdin(&["--widths", "1,3,2", "--output-delimiter", ","], "ab\n", "a,b,\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_fixed_width() -> TestResult {
+    dies(&[CSV, "--widths", "10,0"], "invalid value '0' for '--widths <WIDTHS>'")?;
+    dies(&[CSV, "--spec", "tests/inputs/books.csv"], "tests/inputs/books.csv: TOML parse error")?;
+    dies(&[CSV, "--spec", "tests/inputs/books.toml", "-f", "1"], "cannot be used with")
+}
+
+// --------------------------------------------------
+#[test]
+fn json_output() -> TestResult {
+    let input = "id,name,zip,ok\n1,Ann,02134,true\n2,Bob,,false\n";
+    run_stdin(
+        &["-d", ",", "-F", "name,id", "--reorder", "--output", "json"],
+        input,
+        "[\n{\"name\":\"Ann\",\"id\":\"1\"},\n{\"name\":\"Bob\",\"id\":\"2\"}\n]\n",
+    )?;
+    run_stdin(
+        &["-d", ",", "--header", "-f", "1-", "--output", "ndjson", "--infer-types"],
+        input,
+        "{\"id\":1,\"name\":\"Ann\",\"zip\":\"02134\",\"ok\":true}\n\
+         {\"id\":2,\"name\":\"Bob\",\"zip\":null,\"ok\":false}\n",
+    )?;
+    run_stdin(&["-d", ",", "--header", "-f", "1", "--output", "json"], "id\n", "[]\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn json_output_fixed_width() -> TestResult {
+    run_output(
+        &["tests/inputs/books.fixed", "--spec", "tests/inputs/books.toml", "--output", "ndjson"],
+        "{\"last\":\"Zola\",\"first\":\"Émile\",\"year\":\"1865\"}\n\
+         {\"last\":\"Beckett\",\"first\":\"Samuel\",\"year\":\"1952\"}\n\
+         {\"last\":\"Verne\",\"first\":\"Jules\",\"year\":\"1870\"}\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_json_without_names() -> TestResult {
+    dies(&[CSV, "-d", ",", "-f", "1", "--output", "json"], "--output json needs column names")?;
+    dies(&[CSV, "-c", "1", "--output", "ndjson"], "cannot be used with")
+}
+
+// --------------------------------------------------
+#[test]
+fn infer_types_keeps_text() -> TestResult {
+    run_stdin(
+        &["-d", ",", "--header", "-f", "1-", "--output", "ndjson", "--infer-types"],
+        "id,n,x,y\n12345678901234567890123,-3,1.5,1e5\n",
+        "{\"id\":\"12345678901234567890123\",\"n\":-3,\"x\":1.5,\"y\":\"1e5\"}\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_json_repeated_column() -> TestResult {
+    dies(
+        &[CSV, "-d", ",", "-F", "year,year", "--reorder", "--output", "json"],
+        "can't hold column \"year\" more than once",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_infer_types_without_json() -> TestResult {
+    dies(&[CSV, "-f", "1", "--infer-types"], "--infer-types needs --output json or ndjson")
+}
+
+// --------------------------------------------------
+#[test]
+fn only_delimited() -> TestResult {
+    run_stdin(&["-d", ",", "-f", "2", "-s"], "a,b\nsolo\nc,d\n", "b\nd\n")?;
+    run_stdin(&["-w", "-f", "1", "--only-delimited"], "a b\nsolo\n", "a\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn on_error() -> TestResult {
+    let uneven = "tests/inputs/uneven.csv";
+    dies(
+        &[uneven, "-d", ",", "-f", "1"],
+        "tests/inputs/uneven.csv: line 3, byte 14: expected 2 fields, found 1",
+    )?;
+    run_output(&[uneven, "-d", ",", "-f", "1", "--on-error", "skip"], "id\n1\n4\n")?;
+    Command::cargo_bin(PRG)?
+        .args([uneven, "-d", ",", "-f", "2", "--on-error", "warn"])
+        .assert()
+        .success()
+        .stdout("name\nAnn\nDi\n")
+        .stderr(
+            "tests/inputs/uneven.csv: line 3, byte 14: expected 2 fields, found 1\n\
+             tests/inputs/uneven.csv: line 4, byte 16: expected 2 fields, found 3\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn where_conditions() -> TestResult {
+    let books = "tests/inputs/books.csv";
+    run_output(
+        &[books, "-d", ",", "-F", "Author", "--where", "Year>1866"],
+        "Author\nSamuel Beckett\nJules Verne\n",
+    )?;
+    run_output(
+        &[books, "-d", ",", "-F", "Title", "--where", "Year<=1870", "--where", "Author~^J"],
+        "Title\n\"20,000 Leagues Under the Sea\"\n",
+    )?;
+    run_output(
+        &[books, "-d", ",", "--header", "-f", "1", "--where", "2=1865.0"],
+        "Author\nÉmile Zola\n",
+    )?;
+    run_output(&[books, "-d", ",", "-f", "2", "--where", "1!~e"], "Year\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_where() -> TestResult {
+    let books = "tests/inputs/books.csv";
+    dies(&[books, "-d", ",", "-f", "1", "--where", "Year"], "invalid --where \"Year\": expected COLUMN OP VALUE")?;
+    dies(&[books, "-d", ",", "-f", "1", "--where", "0=1"], "fields are numbered from 1")?;
+    dies(&[books, "-d", ",", "-f", "1", "--where", "Year~("], "invalid --where \"Year~(\": regex parse error")?;
+    dies(&[books, "-d", ",", "-f", "1", "--where", "Year>1"], "--where column \"Year\" needs a header row")?;
+    dies(&[books, "-d", ",", "-F", "Title", "--where", "year>1"], "unknown column \"year\"")
+}
+
+// --------------------------------------------------
+#[test]
+fn zero_terminated() -> TestResult {
+    run_bytes(&["-z", "-d", ",", "-f", "2"], b"a,b\0c,d\0", b"b\0d\0")?;
+    run_bytes(&["-z", "-c", "2-3"], "h\u{e9}llo\0w\u{f6}rld".as_bytes(), "\u{e9}l\0\u{f6}r\0".as_bytes())?;
+    run_bytes(&["--zero-terminated", "-b", "1"], b"ab\ncd\0ef", b"a\0e\0")
+}
+
+// --------------------------------------------------
+#[test]
+fn record_separator() -> TestResult {
+    run_stdin(&["--record-separator", ";", "-w", "-f", "2"], "a b;c d", "b;d;")?;
+    run_stdin(&["--record-separator", ";", "--widths", "1,1", "--output-delimiter", ","], "ab;cd;", "a,b;c,d;")?;
+    dies(&[CSV, "-f", "1", "--record-separator", ";;"], "--record-separator \";;\" must be a single byte")
 }
@@ -0,0 +1,49 @@
+use csv::StringRecord;
+use cutr::{Selector, SelectorError};
+
+type TestResult = Result<(), SelectorError>;
+
+// --------------------------------------------------
+#[test]
+fn fields() -> TestResult {
+    let record = StringRecord::from(vec!["a", "b", "c", "d"]);
+    assert_eq!(Selector::parse("1,3-")?.fields(&record), ["a", "c", "d"]);
+    assert_eq!(Selector::parse_in_order("4,1,1")?.fields(&record), ["d", "a", "a"]);
+    // Positions past the end of the record are skipped.
+    assert_eq!(Selector::parse("2,9")?.fields(&record), ["b"]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn complement() -> TestResult {
+    let selector = Selector::parse("2-3,5")?.complement();
+    assert_eq!(selector.ranges(), [0..1, 3..4, 5..usize::MAX]);
+    assert_eq!(selector.chars("abcdefg"), "adfg");
+    assert!(Selector::parse("1-")?.complement().ranges().is_empty());
+    assert_eq!(selector.complement(), Selector::parse("2-3,5")?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn sorts_and_merges() -> TestResult {
+    assert_eq!(Selector::parse("5,1-2,2-3")?.ranges(), [0..3, 4..5]);
+    assert_eq!(Selector::parse("-2,4-")?.bytes(b"abcdef"), b"abdef");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn errors() {
+    let error = |spec: &str| Selector::parse(spec).unwrap_err();
+    assert_eq!(error("0"), SelectorError::Zero);
+    assert_eq!(error("0-2"), SelectorError::Zero);
+    assert_eq!(error("3-1"), SelectorError::DecreasingRange);
+    assert_eq!(error("-0"), SelectorError::DecreasingRange);
+    assert_eq!(error("-"), SelectorError::NoEndpoint);
+    assert_eq!(error("1-x"), SelectorError::InvalidValue("x".to_string()));
+    assert_eq!(error("+1"), SelectorError::InvalidValue("+1".to_string()));
+    assert_eq!(error("1,,3"), SelectorError::Empty);
+    assert_eq!(error(""), SelectorError::Empty);
+}
@@ -0,0 +1,30 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use regex::bytes::Regex;
+
+/// How much of a file is inspected for NUL bytes to decide it's binary.
+const BINARY_PROBE_LEN: usize = 8192;
+
+/// Returns whether any line of the file at `path` matches one of
+/// `patterns`, stopping at the first match. Unless `binary` is set, files
+/// with a NUL byte near the start are treated as binary and never match.
+pub fn contains(path: &Path, patterns: &[Regex], binary: bool) -> io::Result<bool> {
+    let mut reader = BufReader::with_capacity(BINARY_PROBE_LEN, File::open(path)?);
+    if !binary && reader.fill_buf()?.contains(&0) {
+        return Ok(false);
+    }
+    let mut line = vec![];
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(false);
+        }
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+        if patterns.iter().any(|re| re.is_match(&line)) {
+            return Ok(true);
+        }
+    }
+}
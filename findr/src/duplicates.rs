@@ -0,0 +1,104 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{File, Metadata};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// How many leading bytes are hashed before committing to a full read.
+const PARTIAL_LEN: u64 = 4096;
+
+/// A set of files with identical contents.
+#[derive(Debug, Serialize)]
+pub struct DuplicateGroup {
+    pub size: u64,
+    pub hash: String,
+    pub paths: Vec<String>,
+}
+
+/// Groups `files` (path and metadata) into sets of identical files.
+///
+/// Candidates are narrowed by size, then by a hash of the first
+/// `PARTIAL_LEN` bytes, then by a hash of the whole content, so most
+/// files are never read in full. Files that can't be read are reported
+/// on stderr and left out.
+pub fn find_duplicates(files: Vec<(PathBuf, Metadata)>) -> Vec<DuplicateGroup> {
+    // The same file can be reached twice, under different spellings, when
+    // search paths overlap, and hard links are the same file too.
+    let mut seen: HashSet<FileId> = HashSet::new();
+    let mut by_size: BTreeMap<u64, Vec<PathBuf>> = BTreeMap::new();
+    for (path, metadata) in files {
+        let id = match file_id(&path, &metadata) {
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                continue;
+            }
+            Ok(id) => id,
+        };
+        if seen.insert(id) {
+            by_size.entry(metadata.len()).or_default().push(path);
+        }
+    }
+
+    let mut groups = vec![];
+    for (size, paths) in by_size {
+        if paths.len() < 2 {
+            continue;
+        }
+        for (partial, paths) in group_by_hash(paths, Some(PARTIAL_LEN)) {
+            // The partial hash already covered every byte of small files.
+            let full = if size <= PARTIAL_LEN {
+                vec![(partial, paths)]
+            } else {
+                group_by_hash(paths, None)
+            };
+            for (hash, paths) in full {
+                let mut paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+                paths.sort();
+                groups.push(DuplicateGroup { size, hash, paths });
+            }
+        }
+    }
+    groups
+}
+
+/// Identifies the file behind a path, however it was spelled.
+#[cfg(unix)]
+type FileId = (u64, u64);
+
+#[cfg(unix)]
+fn file_id(_path: &Path, metadata: &Metadata) -> io::Result<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+type FileId = PathBuf;
+
+#[cfg(not(unix))]
+fn file_id(path: &Path, _metadata: &Metadata) -> io::Result<FileId> {
+    std::fs::canonicalize(path)
+}
+
+fn group_by_hash(paths: Vec<PathBuf>, limit: Option<u64>) -> Vec<(String, Vec<PathBuf>)> {
+    let mut by_hash: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for path in paths {
+        match hash_file(&path, limit) {
+            Err(e) => eprintln!("{}: {}", path.display(), e),
+            Ok(hash) => by_hash.entry(hash).or_default().push(path),
+        }
+    }
+    let mut groups: Vec<_> = by_hash.into_iter().filter(|(_, paths)| paths.len() > 1).collect();
+    groups.sort();
+    groups
+}
+
+fn hash_file(path: &Path, limit: Option<u64>) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    match limit {
+        Some(limit) => io::copy(&mut (&mut file).take(limit), &mut hasher)?,
+        None => io::copy(&mut file, &mut hasher)?,
+    };
+    Ok(format!("{:x}", hasher.finalize()))
+}
@@ -1,6 +1,18 @@
+mod content;
+mod duplicates;
+mod times;
+mod watch;
+
 use std::error::Error;
+use std::ffi::OsStr;
+use std::fs::{self, Metadata};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 use clap::{builder, Command, ArgAction, Arg, ValueEnum, value_parser};
 use regex::Regex;
+use serde::Serialize;
+use times::{TimeField, TimePredicate};
+use watch::Change;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
@@ -31,6 +43,154 @@ pub struct Config {
     paths: Vec<String>,
     names: Vec<Regex>,
     entry_types: Vec<EntryType>,
+    json: bool,
+    duplicates: bool,
+    min_size: u64,
+    contains: Vec<regex::bytes::Regex>,
+    binary: bool,
+    times: Vec<TimePredicate>,
+    now: SystemTime,
+    watch: bool,
+}
+
+impl Config {
+    /// `now` is what `--mmin` and friends measure age from.
+    fn matches(&self, entry: &walkdir::DirEntry, now: SystemTime) -> bool {
+        let type_match = self.entry_types.is_empty()
+            || self.entry_types.iter().any(|entry_type| {
+                match entry_type {
+                    EntryType::Link => entry.file_type().is_symlink(),
+                    EntryType::Dir => entry.file_type().is_dir(),
+                    EntryType::File => entry.file_type().is_file()
+                }});
+        type_match
+            && self.name_matches(entry.file_name())
+            && self.time_matches(entry, now)
+            && self.content_matches(entry)
+    }
+
+    fn name_matches(&self, name: &OsStr) -> bool {
+        self.names.is_empty()
+        || self.names.iter().any(|re| re.is_match(&name.to_string_lossy(),))
+    }
+
+    fn time_matches(&self, entry: &walkdir::DirEntry, now: SystemTime) -> bool {
+        if self.times.is_empty() {
+            return true;
+        }
+        match entry.metadata() {
+            Err(e) => {
+                eprintln!("{}: {}", entry.path().display(), e);
+                false
+            }
+            Ok(metadata) => self.times.iter().all(|t| t.matches(&metadata, now)),
+        }
+    }
+
+    /// Content predicates are checked last since they read the file.
+    fn content_matches(&self, entry: &walkdir::DirEntry) -> bool {
+        if self.contains.is_empty() {
+            return true;
+        }
+        if !entry.file_type().is_file() {
+            return false;
+        }
+        content::contains(entry.path(), &self.contains, self.binary).unwrap_or_else(|e| {
+            eprintln!("{}: {}", entry.path().display(), e);
+            false
+        })
+    }
+
+    /// Walks `path`, reporting unreadable entries on stderr and yielding
+    /// the ones that pass the type and name filters.
+    fn walk<'a>(&'a self, path: &str) -> impl Iterator<Item = walkdir::DirEntry> + 'a {
+        walkdir::WalkDir::new(path)
+            .into_iter()
+            .filter_map(|e| match e {
+                Err(e) => {
+                    eprintln!("{}", e);
+                    None
+                }
+                Ok(entry) => Some(entry),
+            })
+            .filter(|entry| self.matches(entry, self.now))
+    }
+}
+
+/// One line of `--json` output, describing a matched entry without
+/// following symlinks.
+#[derive(Debug, Serialize)]
+struct EntryInfo {
+    path: String,
+    #[serde(rename = "type")]
+    entry_type: &'static str,
+    size: u64,
+    mode: Option<String>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    mtime: Option<i64>,
+    atime: Option<i64>,
+    ctime: Option<i64>,
+    depth: usize,
+    target: Option<String>,
+}
+
+impl EntryInfo {
+    fn new(entry: &walkdir::DirEntry) -> MyResult<Self> {
+        let metadata = entry.metadata()?;
+        let file_type = entry.file_type();
+        let entry_type = if file_type.is_symlink() {
+            "link"
+        } else if file_type.is_dir() {
+            "dir"
+        } else if file_type.is_file() {
+            "file"
+        } else {
+            "other"
+        };
+        let target = if file_type.is_symlink() {
+            Some(fs::read_link(entry.path())?.display().to_string())
+        } else {
+            None
+        };
+        let (mode, uid, gid, ctime) = unix_fields(&metadata);
+        Ok(EntryInfo {
+            path: entry.path().display().to_string(),
+            entry_type,
+            size: metadata.len(),
+            mode,
+            uid,
+            gid,
+            mtime: metadata.modified().ok().map(epoch_secs),
+            atime: metadata.accessed().ok().map(epoch_secs),
+            ctime,
+            depth: entry.depth(),
+            target,
+        })
+    }
+}
+
+fn epoch_secs(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    }
+}
+
+#[cfg(unix)]
+fn unix_fields(metadata: &Metadata) -> (Option<String>, Option<u32>, Option<u32>, Option<i64>) {
+    use std::os::unix::fs::MetadataExt;
+    (
+        Some(format!("{:04o}", metadata.mode() & 0o7777)),
+        Some(metadata.uid()),
+        Some(metadata.gid()),
+        Some(metadata.ctime()),
+    )
+}
+
+#[cfg(not(unix))]
+fn unix_fields(_metadata: &Metadata) -> (Option<String>, Option<u32>, Option<u32>, Option<i64>) {
+    (None, None, None, None)
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -59,14 +219,109 @@ pub fn get_args() -> MyResult<Config> {
             .help("Entry type")
             .action(ArgAction::Append)
         )
+        .arg(
+            Arg::new("json")
+            .long("json")
+            .action(ArgAction::SetTrue)
+            .help("Print one JSON object with metadata per entry")
+        )
+        .arg(
+            Arg::new("duplicates")
+            .long("duplicates")
+            .action(ArgAction::SetTrue)
+            .help("Report groups of files with identical contents")
+        )
+        .arg(
+            Arg::new("min_size")
+            .long("min-size")
+            .value_name("BYTES")
+            .value_parser(value_parser!(u64))
+            .default_value("1")
+            .requires("duplicates")
+            .help("Ignore files smaller than BYTES in --duplicates mode")
+        )
+        .arg(
+            Arg::new("contains")
+            .long("contains")
+            .value_name("REGEX")
+            .action(ArgAction::Append)
+            .help("Files with a line matching REGEX")
+        )
+        .arg(
+            Arg::new("icontains")
+            .long("icontains")
+            .value_name("REGEX")
+            .action(ArgAction::Append)
+            .help("Like --contains, but case-insensitive")
+        )
+        .arg(
+            Arg::new("binary")
+            .long("binary")
+            .action(ArgAction::SetTrue)
+            .help("Also search binary files for --contains")
+        )
+        .arg(
+            Arg::new("newer")
+            .long("newer")
+            .value_name("FILE")
+            .action(ArgAction::Append)
+            .help("Modified more recently than FILE was modified")
+        )
+        .arg(
+            Arg::new("anewer")
+            .long("anewer")
+            .value_name("FILE")
+            .action(ArgAction::Append)
+            .help("Accessed more recently than FILE was modified")
+        )
+        .arg(
+            Arg::new("cnewer")
+            .long("cnewer")
+            .value_name("FILE")
+            .action(ArgAction::Append)
+            .help("Status changed more recently than FILE was modified")
+        )
+        .arg(
+            Arg::new("mmin")
+            .long("mmin")
+            .value_name("N")
+            .value_parser(times::parse_minutes)
+            .allow_hyphen_values(true)
+            .action(ArgAction::Append)
+            .help("Modified N minutes ago (+N more than, -N less than)")
+        )
+        .arg(
+            Arg::new("amin")
+            .long("amin")
+            .value_name("N")
+            .value_parser(times::parse_minutes)
+            .allow_hyphen_values(true)
+            .action(ArgAction::Append)
+            .help("Accessed N minutes ago (+N more than, -N less than)")
+        )
+        .arg(
+            Arg::new("cmin")
+            .long("cmin")
+            .value_name("N")
+            .value_parser(times::parse_minutes)
+            .allow_hyphen_values(true)
+            .action(ArgAction::Append)
+            .help("Status changed N minutes ago (+N more than, -N less than)")
+        )
+        .arg(
+            Arg::new("watch")
+            .long("watch")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("duplicates")
+            .help("Keep running and report matching entries as they change")
+        )
         .get_matches();
 
     let paths: Vec<String> = cmd.get_many::<String>("paths").unwrap().map(|e| e.to_string()).collect();
 
-    let matched_names = cmd.get_many::<String>("names");
     let mut names: Vec<Regex> = vec![];
-    if !matched_names.is_none() {
-        for name in matched_names.unwrap() {
+    if let Some(matched_names) = cmd.get_many::<String>("names") {
+        for name in matched_names {
             match Regex::new(name) {
                 Err(_) => {
                     std::panic!("Invalid --name \"{}\"", name);
@@ -76,43 +331,149 @@ pub fn get_args() -> MyResult<Config> {
         }
     }
 
-    let matched_entry_types = cmd.get_many::<EntryType>("entry_types");
-    let mut entry_types: Vec<EntryType> = vec![];
-    if !matched_entry_types.is_none() {
-        entry_types = matched_entry_types.unwrap().map(|e| e.to_owned()).collect();
+    let entry_types: Vec<EntryType> = cmd.get_many::<EntryType>("entry_types")
+        .map(|matched| matched.map(|e| e.to_owned()).collect())
+        .unwrap_or_default();
+    let mut contains: Vec<regex::bytes::Regex> = vec![];
+    for (arg, case_insensitive) in [("contains", false), ("icontains", true)] {
+        for pattern in cmd.get_many::<String>(arg).unwrap_or_default() {
+            match regex::bytes::RegexBuilder::new(pattern).case_insensitive(case_insensitive).build() {
+                Err(_) => return Err(From::from(format!("Invalid --{} \"{}\"", arg, pattern))),
+                Ok(r) => contains.push(r),
+            }
+        }
+    }
+
+    let mut times: Vec<TimePredicate> = vec![];
+    for (arg, field) in [("newer", TimeField::Modified), ("anewer", TimeField::Accessed), ("cnewer", TimeField::Changed)] {
+        for filename in cmd.get_many::<String>(arg).unwrap_or_default() {
+            let reference = fs::symlink_metadata(filename)
+                .and_then(|metadata| metadata.modified())
+                .map_err(|e| format!("{}: {}", filename, e))?;
+            times.push(TimePredicate::Newer(field, reference));
+        }
+    }
+    for (arg, field) in [("mmin", TimeField::Modified), ("amin", TimeField::Accessed), ("cmin", TimeField::Changed)] {
+        for minutes in cmd.get_many::<times::Minutes>(arg).unwrap_or_default() {
+            times.push(TimePredicate::Age(field, *minutes));
+        }
     }
-    Ok(Config{ paths, names, entry_types })
+
+    Ok(Config{
+        paths,
+        names,
+        entry_types,
+        json: cmd.get_flag("json"),
+        duplicates: cmd.get_flag("duplicates"),
+        min_size: *cmd.get_one::<u64>("min_size").unwrap(),
+        contains,
+        binary: cmd.get_flag("binary"),
+        times,
+        now: SystemTime::now(),
+        watch: cmd.get_flag("watch"),
+    })
 }
 
 pub fn run(config: Config) -> MyResult<()> {
-    let type_filter = |entry: &walkdir::DirEntry| {
-        config.entry_types.is_empty()
-        || config.entry_types.iter().any(|entry_type| {
-            match entry_type {
-                EntryType::Link => entry.file_type().is_symlink(),
-                EntryType::Dir => entry.file_type().is_dir(),
-                EntryType::File => entry.file_type().is_file()
-            }})
+    if config.duplicates {
+        return run_duplicates(&config);
+    }
+    if config.watch {
+        return watch::watch(
+            &config.paths,
+            || print_entries(&config),
+            |path, change| print_change(&config, path, change),
+        );
+    }
+    print_entries(&config)
+}
+
+fn print_entries(config: &Config) -> MyResult<()> {
+    for path in &config.paths {
+        let entries = config.walk(path);
+        if config.json {
+            for entry in entries {
+                match EntryInfo::new(&entry) {
+                    Err(e) => eprintln!("{}: {}", entry.path().display(), e),
+                    Ok(info) => println!("{}", serde_json::to_string(&info)?),
+                }
+            }
+        } else {
+            let entries = entries
+                .map(|entry| entry.path().display().to_string())
+                .collect::<Vec<_>>();
+            println!("{}", entries.join("\n"));
+        }
+    }
+    Ok(())
+}
+
+/// Prints one `--watch` change as `EVENT<TAB>PATH`, or as a JSON entry
+/// with an extra `event` key. Deleted entries can't be inspected anymore,
+/// so only the name filter applies to them. Ages are measured from the
+/// event rather than from startup, since watching can go on for hours.
+fn print_change(config: &Config, path: &Path, change: Change) -> MyResult<()> {
+    let entry = match change {
+        Change::Deleted => None,
+        Change::Created | Change::Modified => {
+            match walkdir::WalkDir::new(path).max_depth(0).into_iter().next() {
+                Some(Ok(entry)) => Some(entry),
+                // Removed again before we got to look at it.
+                _ => return Ok(()),
+            }
+        }
     };
-    let name_filter = |entry: &walkdir::DirEntry| {
-        config.names.is_empty()
-        || config.names.iter().any(|re| re.is_match(&entry.file_name().to_string_lossy(),))
+    let matched = match &entry {
+        Some(entry) => config.matches(entry, SystemTime::now()),
+        None => path.file_name().is_some_and(|name| config.name_matches(name)),
     };
-    for path in config.paths {
-        let entries = walkdir::WalkDir::new(path)
-            .into_iter()
-            .filter_map(|e| match e {
+    if !matched {
+        return Ok(());
+    }
+
+    if config.json {
+        let mut value = match &entry {
+            Some(entry) => match EntryInfo::new(entry) {
                 Err(e) => {
-                    eprintln!("{}", e);
-                    None
+                    eprintln!("{}: {}", path.display(), e);
+                    return Ok(());
                 }
-                Ok(entry) => Some(entry),
-            })
-            .filter(type_filter)
-            .filter(name_filter)
-            .map(|entry| entry.path().display().to_string())
+                Ok(info) => serde_json::to_value(info)?,
+            },
+            None => serde_json::json!({ "path": path.display().to_string() }),
+        };
+        value["event"] = change.as_str().into();
+        println!("{}", value);
+    } else {
+        println!("{}\t{}", change.as_str(), path.display());
+    }
+    Ok(())
+}
+
+fn run_duplicates(config: &Config) -> MyResult<()> {
+    let mut files = vec![];
+    for path in &config.paths {
+        for entry in config.walk(path).filter(|entry| entry.file_type().is_file()) {
+            match entry.metadata() {
+                Err(e) => eprintln!("{}: {}", entry.path().display(), e),
+                Ok(metadata) if metadata.len() >= config.min_size => {
+                    files.push((entry.into_path(), metadata));
+                }
+                Ok(_) => {}
+            }
+        }
+    }
+    let groups = duplicates::find_duplicates(files);
+    if config.json {
+        for group in groups {
+            println!("{}", serde_json::to_string(&group)?);
+        }
+    } else {
+        let groups = groups
+            .iter()
+            .map(|group| group.paths.join("\n"))
             .collect::<Vec<_>>();
-        println!("{}", entries.join("\n"));
+        println!("{}", groups.join("\n\n"));
     }
     Ok(())
 }
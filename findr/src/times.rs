@@ -0,0 +1,92 @@
+use std::fs::Metadata;
+use std::time::SystemTime;
+
+/// Which timestamp of an entry a predicate looks at.
+#[derive(Debug, Clone, Copy)]
+pub enum TimeField {
+    Accessed,
+    Changed,
+    Modified,
+}
+
+impl TimeField {
+    pub fn of(self, metadata: &Metadata) -> Option<SystemTime> {
+        match self {
+            TimeField::Accessed => metadata.accessed().ok(),
+            TimeField::Changed => changed(metadata),
+            TimeField::Modified => metadata.modified().ok(),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn changed(metadata: &Metadata) -> Option<SystemTime> {
+    use std::os::unix::fs::MetadataExt;
+    use std::time::{Duration, UNIX_EPOCH};
+    let since_epoch = Duration::new(metadata.ctime().try_into().ok()?, metadata.ctime_nsec().try_into().ok()?);
+    UNIX_EPOCH.checked_add(since_epoch)
+}
+
+#[cfg(not(unix))]
+fn changed(_metadata: &Metadata) -> Option<SystemTime> {
+    None
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Comparison {
+    MoreThan,
+    LessThan,
+    Exactly,
+}
+
+/// A `+N`, `-N` or `N` argument to `--mmin` and friends.
+#[derive(Debug, Clone, Copy)]
+pub struct Minutes {
+    comparison: Comparison,
+    count: u64,
+}
+
+pub fn parse_minutes(txt: &str) -> Result<Minutes, String> {
+    let (comparison, digits) = match txt.as_bytes().first() {
+        Some(b'+') => (Comparison::MoreThan, &txt[1..]),
+        Some(b'-') => (Comparison::LessThan, &txt[1..]),
+        _ => (Comparison::Exactly, txt),
+    };
+    match digits.parse() {
+        Ok(count) if !digits.starts_with('+') => Ok(Minutes { comparison, count }),
+        _ => Err(format!("expected N, +N or -N minutes, got \"{}\"", txt)),
+    }
+}
+
+#[derive(Debug)]
+pub enum TimePredicate {
+    /// The field is more recent than the reference file's modification time.
+    Newer(TimeField, SystemTime),
+    /// The field is `Minutes` old, measured from when findr started, or
+    /// from each change under `--watch`.
+    Age(TimeField, Minutes),
+}
+
+impl TimePredicate {
+    pub fn matches(&self, metadata: &Metadata, now: SystemTime) -> bool {
+        match self {
+            TimePredicate::Newer(field, reference) => {
+                field.of(metadata).is_some_and(|time| time > *reference)
+            }
+            TimePredicate::Age(field, minutes) => field.of(metadata).is_some_and(|time| {
+                let age = match now.duration_since(time) {
+                    Ok(d) => d.as_secs_f64(),
+                    Err(e) => -e.duration().as_secs_f64(),
+                };
+                let limit = minutes.count as f64 * 60.0;
+                // Same rounding as GNU find: `N` means more than N-1 and
+                // at most N minutes ago.
+                match minutes.comparison {
+                    Comparison::MoreThan => age > limit,
+                    Comparison::LessThan => age < limit,
+                    Comparison::Exactly => age > limit - 60.0 && age <= limit,
+                }
+            }),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use notify::event::{EventKind, ModifyKind, RenameMode};
+use notify::{RecursiveMode, Watcher};
+use crate::MyResult;
+
+/// What happened to a watched path.
+#[derive(Debug, Clone, Copy)]
+pub enum Change {
+    Created,
+    Modified,
+    Deleted,
+}
+
+impl Change {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Change::Created => "created",
+            Change::Modified => "modified",
+            Change::Deleted => "deleted",
+        }
+    }
+}
+
+/// Watches `paths` recursively, calling `on_change` for every change until
+/// the watcher fails. `ready` runs once the watches are in place, so
+/// nothing that happens while it runs is missed.
+pub fn watch<R, F>(paths: &[String], ready: R, mut on_change: F) -> MyResult<()>
+where
+    R: FnOnce() -> MyResult<()>,
+    F: FnMut(&Path, Change) -> MyResult<()>,
+{
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    // Events come with canonical paths; report them relative to the path
+    // they were found under, like the initial walk does.
+    let mut roots = vec![];
+    for path in paths {
+        match watcher.watch(Path::new(path), RecursiveMode::Recursive) {
+            Err(e) => eprintln!("{}: {}", path, e),
+            Ok(()) => roots.push((fs::canonicalize(path)?, PathBuf::from(path))),
+        }
+    }
+    // Otherwise no event would ever come, and we'd wait forever.
+    if roots.is_empty() {
+        return Err(From::from("--watch: none of the paths could be watched"));
+    }
+    ready()?;
+
+    for event in rx {
+        let event = event?;
+        for (path, change) in changes(event.kind, event.paths) {
+            let path = roots
+                .iter()
+                .find_map(|(canonical, given)| {
+                    path.strip_prefix(canonical).ok().map(|rest| given.join(rest))
+                })
+                .unwrap_or(path);
+            on_change(&path, change)?;
+        }
+    }
+    Ok(())
+}
+
+fn changes(kind: EventKind, paths: Vec<PathBuf>) -> Vec<(PathBuf, Change)> {
+    let change = match kind {
+        EventKind::Create(_) => Change::Created,
+        EventKind::Remove(_) => Change::Deleted,
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => Change::Deleted,
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => Change::Created,
+        // Follows the separate `From` and `To` events for the same rename.
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => return vec![],
+        EventKind::Modify(_) => Change::Modified,
+        EventKind::Access(_) | EventKind::Any | EventKind::Other => return vec![],
+    };
+    paths.into_iter().map(|path| (path, change)).collect()
+}
@@ -1,7 +1,7 @@
 use assert_cmd::Command;
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
-use std::{borrow::Cow, fs, path::Path};
+use std::{borrow::Cow, fs, path::{Path, PathBuf}};
 
 type TestResult = Result<(), Box<dyn std::error::Error>>;
 
@@ -22,6 +22,32 @@ fn gen_bad_file() -> String {
     }
 }
 
+// --------------------------------------------------
+/// Makes an empty directory to build a test tree in.
+fn temp_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("findr-{}", gen_bad_file()));
+    fs::create_dir(&dir)?;
+    Ok(dir)
+}
+
+// --------------------------------------------------
+/// Runs findr on `dir` and returns the base names it prints, sorted.
+fn find_names(dir: &Path, args: &[&str]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let cmd = Command::cargo_bin(PRG)?
+        .arg(dir.to_str().unwrap())
+        .args(args)
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let mut names: Vec<String> = stdout
+        .lines()
+        .filter(|s| !s.is_empty())
+        .map(|s| Path::new(s).file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
 // --------------------------------------------------
 #[test]
 fn skips_bad_dir() -> TestResult {
@@ -39,7 +65,7 @@ fn skips_bad_dir() -> TestResult {
 #[test]
 fn dies_bad_name() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["--name", "*.csv"])
+        .args(["--name", "*.csv"])
         .assert()
         .failure()
         .stderr(predicate::str::contains("Invalid --name \"*.csv\""));
@@ -51,7 +77,7 @@ fn dies_bad_name() -> TestResult {
 fn dies_bad_type() -> TestResult {
     let expected = "error: invalid value 'x' for '--type <entry_types>'";
     Command::cargo_bin(PRG)?
-        .args(&["--type", "x"])
+        .args(["--type", "x"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -60,14 +86,14 @@ fn dies_bad_type() -> TestResult {
 
 // --------------------------------------------------
 #[cfg(windows)]
-fn format_file_name(expected_file: &str) -> Cow<str> {
+fn format_file_name(expected_file: &str) -> Cow<'_, str> {
     // Equivalent to: Cow::Owned(format!("{}.windows", expected_file))
     format!("{}.windows", expected_file).into()
 }
 
 // --------------------------------------------------
 #[cfg(not(windows))]
-fn format_file_name(expected_file: &str) -> Cow<str> {
+fn format_file_name(expected_file: &str) -> Cow<'_, str> {
     // Equivalent to: Cow::Borrowed(expected_file)
     expected_file.into()
 }
@@ -295,7 +321,7 @@ fn unreadable_dir() -> TestResult {
     //permissions.set_mode(0o000);
 
     std::process::Command::new("chmod")
-        .args(&["000", dirname])
+        .args(["000", dirname])
         .status()
         .expect("failed");
 
@@ -316,3 +342,306 @@ fn unreadable_dir() -> TestResult {
     assert!(stderr.contains("cant-touch-this: Permission denied"));
     Ok(())
 }
+
+// --------------------------------------------------
+fn run_json(args: &[&str]) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error>> {
+    let cmd = Command::cargo_bin(PRG)?.args(args).assert().success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let mut entries = vec![];
+    for line in stdout.lines() {
+        entries.push(serde_json::from_str(line)?);
+    }
+    Ok(entries)
+}
+
+// --------------------------------------------------
+#[test]
+fn json_type_f_name_txt() -> TestResult {
+    let entries = run_json(&["tests/inputs/d", "-t", "f", "-n", "txt", "--json"])?;
+    assert_eq!(entries.len(), 1);
+    let entry = &entries[0];
+    assert_eq!(entry["path"], format!("tests/inputs/d{}d.txt", std::path::MAIN_SEPARATOR));
+    assert_eq!(entry["type"], "file");
+    assert_eq!(entry["size"], 3);
+    assert_eq!(entry["depth"], 1);
+    assert!(entry["mtime"].is_i64());
+    assert!(entry["target"].is_null());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn json_type_l() -> TestResult {
+    let entries = run_json(&["tests/inputs", "-t", "l", "--json"])?;
+    assert_eq!(entries.len(), 1);
+    let entry = &entries[0];
+    assert_eq!(entry["path"], "tests/inputs/d/b.csv");
+    assert_eq!(entry["type"], "link");
+    assert_eq!(entry["target"], "a/b/b.csv");
+    assert_eq!(entry["depth"], 2);
+    assert!(entry["mode"].is_string());
+    assert!(entry["uid"].is_u64());
+    assert!(entry["ctime"].is_i64());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn duplicates() -> TestResult {
+    let entries = run_json(&["tests/inputs", "--duplicates", "--json"])?;
+    assert_eq!(entries.len(), 1);
+    let group = &entries[0];
+    assert_eq!(group["size"], 3);
+    assert_eq!(group["paths"].as_array().unwrap().len(), 8);
+
+    let entries = run_json(&["tests/inputs", "--duplicates", "--json", "-n", "mp3"])?;
+    assert_eq!(entries[0]["paths"].as_array().unwrap().len(), 2);
+
+    let entries = run_json(&["tests/inputs", "--duplicates", "--json", "--min-size", "4"])?;
+    assert!(entries.is_empty());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn duplicates_overlapping_roots() -> TestResult {
+    let entries = run_json(&["tests/inputs", "./tests/inputs/a", "--duplicates", "--json", "-n", "^a"])?;
+    assert!(entries.is_empty());
+
+    let entries = run_json(&["tests/inputs", "./tests/inputs/d", "--duplicates", "--json"])?;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["paths"].as_array().unwrap().len(), 8);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn duplicates_same_prefix() -> TestResult {
+    let dir = temp_dir()?;
+    let prefix = "x".repeat(10_000);
+    fs::write(dir.join("a"), format!("{}a", prefix))?;
+    fs::write(dir.join("b"), format!("{}b", prefix))?;
+    fs::write(dir.join("c"), format!("{}a", prefix))?;
+
+    let cmd = Command::cargo_bin(PRG)?
+        .args([dir.to_str().unwrap(), "--duplicates"])
+        .assert()
+        .success();
+    fs::remove_dir_all(&dir)?;
+
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines,
+        [dir.join("a").display().to_string(), dir.join("c").display().to_string()]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_min_size_without_duplicates() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--min-size", "10"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--duplicates"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn contains() -> TestResult {
+    let dir = temp_dir()?;
+    fs::write(dir.join("a.toml"), "[package]\nedition = \"2018\"\n")?;
+    fs::write(dir.join("b.toml"), "[package]\nedition = \"2021\"\n")?;
+    fs::write(dir.join("c.txt"), "EDITION = \"2018\"\n")?;
+    fs::write(dir.join("d.bin"), b"\0\x01edition = \"2018\"\n")?;
+
+    let find = |args: &[&str]| find_names(&dir, args);
+
+    let edition = r#"edition = "2018""#;
+    let results = [
+        find(&["--contains", edition])?,
+        find(&["--contains", edition, "-n", "[.]toml$"])?,
+        find(&["--icontains", edition])?,
+        find(&["--contains", edition, "--binary"])?,
+    ];
+    fs::remove_dir_all(&dir)?;
+
+    assert_eq!(results[0], ["a.toml"]);
+    assert_eq!(results[1], ["a.toml"]);
+    assert_eq!(results[2], ["a.toml", "c.txt"]);
+    assert_eq!(results[3], ["a.toml", "d.bin"]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_contains() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--icontains", "*.csv"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --icontains \"*.csv\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn newer_and_mmin() -> TestResult {
+    use std::time::{Duration, SystemTime};
+
+    let dir = temp_dir()?;
+    let now = SystemTime::now();
+    for (name, secs_ago) in [("old", 8970), ("marker", 3570), ("recent", 570), ("new", 0)] {
+        let path = dir.join(name);
+        fs::write(&path, "")?;
+        let time = now - Duration::from_secs(secs_ago);
+        let times = fs::FileTimes::new().set_modified(time).set_accessed(time);
+        fs::File::options().write(true).open(&path)?.set_times(times)?;
+    }
+    let marker = dir.join("marker");
+
+    let find = |args: &[&str]| find_names(&dir, &[&["-t", "f"], args].concat());
+
+    let results = [
+        find(&["--newer", marker.to_str().unwrap()])?,
+        find(&["--anewer", marker.to_str().unwrap()])?,
+        find(&["--mmin", "-60"])?,
+        find(&["--mmin", "+60"])?,
+        find(&["--mmin", "10"])?,
+        find(&["--amin", "+10", "--amin", "-100"])?,
+        find(&["--cmin", "-5"])?,
+    ];
+    fs::remove_dir_all(&dir)?;
+
+    assert_eq!(results[0], ["new", "recent"]);
+    assert_eq!(results[1], ["new", "recent"]);
+    assert_eq!(results[2], ["marker", "new", "recent"]);
+    assert_eq!(results[3], ["old"]);
+    assert_eq!(results[4], ["recent"]);
+    assert_eq!(results[5], ["marker"]);
+    assert_eq!(results[6], ["marker", "new", "old", "recent"]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_mmin() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--mmin", "+-5"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value '+-5' for '--mmin <N>'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_missing_newer_reference() -> TestResult {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["--newer", &bad])
+        .assert()
+        .failure()
+        .stderr(predicate::str::is_match(format!("{}: .* [(]os error 2[)]", bad))?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_watch_nothing() -> TestResult {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args([&bad, "--watch"])
+        .timeout(std::time::Duration::from_secs(5))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("none of the paths could be watched"));
+    Ok(())
+}
+
+// --------------------------------------------------
+/// Starts `findr --watch` and hands back its output line by line, so a
+/// test can wait for each change without blocking forever.
+#[cfg(target_os = "linux")]
+fn spawn_watch(
+    args: &[&str],
+) -> Result<(std::process::Child, std::sync::mpsc::Receiver<String>), Box<dyn std::error::Error>> {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+        .args(args)
+        .arg("--watch")
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let (tx, rx) = std::sync::mpsc::channel();
+    let stdout = child.stdout.take().unwrap();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            if tx.send(line.unwrap()).is_err() {
+                break;
+            }
+        }
+    });
+    Ok((child, rx))
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn watch() -> TestResult {
+    use std::time::Duration;
+
+    let dir = temp_dir()?;
+    fs::write(dir.join("old.log"), "")?;
+    let dirname = dir.to_str().unwrap();
+
+    let (mut child, rx) = spawn_watch(&[dirname, "--name", "[.]log$"])?;
+    let next_line = || rx.recv_timeout(Duration::from_secs(5));
+
+    let result = (|| -> TestResult {
+        assert_eq!(next_line()?, format!("{}/old.log", dirname));
+        fs::write(dir.join("skipped.txt"), "")?;
+        fs::File::create(dir.join("new.log"))?;
+        assert_eq!(next_line()?, format!("created\t{}/new.log", dirname));
+        fs::remove_file(dir.join("old.log"))?;
+        assert_eq!(next_line()?, format!("deleted\t{}/old.log", dirname));
+        Ok(())
+    })();
+    child.kill()?;
+    child.wait()?;
+    fs::remove_dir_all(&dir)?;
+    result
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn watch_mmin() -> TestResult {
+    use std::time::Duration;
+
+    let dir = temp_dir()?;
+    let dirname = dir.to_str().unwrap();
+
+    // Measured from startup, a file made afterwards would have a negative
+    // age and never be more than 0 minutes old.
+    let (mut child, rx) = spawn_watch(&[dirname, "--type", "f", "--mmin", "+0"])?;
+    let result = (|| -> TestResult {
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5))?, "");
+        std::thread::sleep(Duration::from_millis(100));
+        fs::File::create(dir.join("new.log"))?;
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5))?,
+            format!("created\t{}/new.log", dirname)
+        );
+        Ok(())
+    })();
+    child.kill()?;
+    child.wait()?;
+    fs::remove_dir_all(&dir)?;
+    result
+}